@@ -1,7 +1,49 @@
 use std::env;
+use std::fs;
 use std::process::Command;
 
+// Version of the ABI shared with ffiapi, which the vendored esbuild-lib declares as
+// `const EsbuildRsAbiVersion`. A mismatched ffiapi still compiles and links, but misinterprets the
+// structs passed across the boundary, so we refuse to build against one. Bump this whenever a
+// repr(C) struct or extern function shared with ffiapi changes.
+//
+// 2: Plugins, contexts, watch and serve, and Message with id, plugin name, line text, suggestion,
+//    notes and detail.
+// 3: GoBuild, GoTransform and GoBuildContextRebuild take a cancellation ID, cancelled with GoCancel.
+// 4: On-resolve and on-load results have an error, set if the plugin callback panicked.
+// 5: GoTransformMany transforms many inputs with one call, and calls back with consecutive results.
+// 6: Build options have stdin, for building from contents that aren't in a file.
+// 7: Build options have fs, a virtual file system that Go reads through callbacks.
+// 8: Options have mangle_props, reserve_props, mangle_quoted and a mangle cache, and build and
+//    transform callbacks receive the resulting mangle cache.
+// 9: Options have drop and drop_labels.
+// 10: Options have jsx_import_source and jsx_dev, for the automatic JSX runtime.
+// 11: Options have supported, overriding whether each JS and CSS feature is supported.
+const FFIAPI_ABI_VERSION: u32 = 11;
+
+fn check_ffiapi_abi_version() {
+    let source = fs::read_to_string("lib/vendor/github.com/evanw/esbuild/pkg/ffiapi/ffiapi.go")
+        .expect("read ffiapi source");
+    let version = source
+        .lines()
+        .find_map(|l| l.trim().strip_prefix("const EsbuildRsAbiVersion = "))
+        .map(|v| v.trim().parse::<u32>().expect("parse ffiapi ABI version"));
+    match version {
+        Some(v) if v == FFIAPI_ABI_VERSION => {}
+        Some(v) => panic!(
+            "esbuild-lib implements ffiapi ABI version {} but version {} is required; update the esbuild-lib submodule",
+            v, FFIAPI_ABI_VERSION
+        ),
+        None => panic!(
+            "esbuild-lib doesn't declare an ffiapi ABI version, but version {} is required; update the esbuild-lib submodule",
+            FFIAPI_ABI_VERSION
+        ),
+    };
+}
+
 fn main() {
+    check_ffiapi_abi_version();
+
     let use_dll = cfg!(target_env = "msvc");
     let out_dir = env::var("OUT_DIR").unwrap();
     let out_name = if use_dll {
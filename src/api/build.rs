@@ -1,21 +1,31 @@
 use std::future::Future;
 use std::os::raw::c_void;
+use std::panic;
 use std::pin::Pin;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, PoisonError};
 use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
 
 use libc::size_t;
 
+use crate::api::cancel::CancelHandle;
+use crate::api::limit;
 use crate::StrContainer;
-use crate::bridge::GoBuild;
-use crate::wrapper::{BuildOptions, BuildResult, Message, OutputFile, SliceContainer};
+use crate::bridge::{FfiapiMangleCacheEntry, GoBuild};
+use crate::error::{EsbuildError, TimeoutError};
+use crate::executor::dispatching;
+use crate::panic::{blocking, catch_panic, forwarding_panics, OnPanic, PanicPayload};
+use crate::timer;
+use crate::wrapper::{
+    mangle_cache_from_ffiapi, BuildOptions, BuildResult, Message, OutputFile, SliceContainer,
+};
 
 struct BuildInvocationData {
     opt_arc_raw: *const BuildOptions,
     cb_trait_ptr: *mut c_void,
 }
 
-extern "C" fn build_callback(
+pub(crate) extern "C" fn build_callback(
     raw_cb_data: *mut c_void,
     metafile: StrContainer,
     raw_output_files: *mut OutputFile,
@@ -24,6 +34,8 @@ extern "C" fn build_callback(
     errors_len: size_t,
     raw_warnings: *mut Message,
     warnings_len: size_t,
+    raw_mangle_cache: *mut FfiapiMangleCacheEntry,
+    mangle_cache_len: size_t,
 ) -> () {
     unsafe {
         let cb_data: Box<BuildInvocationData> = Box::from_raw(raw_cb_data as *mut _);
@@ -46,16 +58,45 @@ extern "C" fn build_callback(
             ptr: raw_warnings,
             len: warnings_len,
         };
+        let mangle_cache = mangle_cache_from_ffiapi(SliceContainer {
+            ptr: raw_mangle_cache,
+            len: mangle_cache_len,
+        });
 
-        rust_cb_trait_box(BuildResult {
-            metafile,
-            output_files,
-            errors,
-            warnings,
+        // A panic can't unwind into Go. Panics are only left to reach here if nothing is waiting on
+        // the build to re-raise them.
+        let _ = catch_panic(|| {
+            rust_cb_trait_box(BuildResult {
+                metafile,
+                output_files,
+                errors,
+                warnings,
+                mangle_cache,
+                timed_out: None,
+            })
         });
     };
 }
 
+// Prepares the cb_data to pass to Go alongside `build_callback`, which will call `cb` with the
+// result and drop the reference held on `options`.
+pub(crate) fn new_build_invocation_data<F>(options: &Arc<BuildOptions>, cb: F) -> *mut c_void
+where
+    F: FnOnce(BuildResult),
+    F: Send + 'static,
+{
+    // Prepare callback.
+    let cb_box = Box::new(cb) as Box<dyn FnOnce(BuildResult)>;
+    let cb_trait_box = Box::new(cb_box);
+    let cb_trait_ptr = Box::into_raw(cb_trait_box);
+
+    let data = Box::into_raw(Box::new(BuildInvocationData {
+        opt_arc_raw: Arc::into_raw(options.clone()),
+        cb_trait_ptr: cb_trait_ptr as *mut c_void,
+    }));
+    data as *mut c_void
+}
+
 /// This function runs an end-to-end build operation. It takes an array of file paths as entry
 /// points, parses them and all of their dependencies, and returns the output files to write to the
 /// file system. The available options roughly correspond to esbuild's command-line flags.
@@ -69,7 +110,12 @@ extern "C" fn build_callback(
 ///
 /// * `options` - Built BuildOptions created from a BuildOptionsBuilder. A reference will be held on
 ///   the Arc until the callback is asynchronously called from Go.
-/// * `cb` - Closure to call once the goroutine completes with the BuildResult.
+/// * `cb` - Closure to call once the goroutine completes with the BuildResult, or to spawn on the
+///   options' executor if set. If it panics, the panic is reported by the panic hook and then
+///   discarded, as it can't unwind into Go and nothing is waiting on the build to re-raise it.
+///
+/// Returns a CancelHandle that can be used to stop the build early. If a concurrency limit has been
+/// set using `set_concurrency_limit`, the build may be queued before it's started.
 ///
 /// # Examples
 ///
@@ -88,53 +134,86 @@ extern "C" fn build_callback(
 ///
 ///   let wg = WaitGroup::new();
 ///   let task = wg.clone();
-///   build_direct(options, |BuildResult { metafile, output_files, errors, warnings }| {
+///   build_direct(options, |BuildResult { metafile, output_files, errors, warnings, .. }| {
 ///     println!("Build complete! Metadata: {}", metafile);
 ///     drop(task);
 ///   });
 ///   wg.wait();
 /// }
 /// ```
-pub fn build_direct<F>(options: Arc<BuildOptions>, cb: F) -> ()
+pub fn build_direct<F>(options: Arc<BuildOptions>, cb: F) -> CancelHandle
 where
     F: FnOnce(BuildResult),
     F: Send + 'static,
 {
-    // Prepare callback.
-    let cb_box = Box::new(cb) as Box<dyn FnOnce(BuildResult)>;
-    let cb_trait_box = Box::new(cb_box);
-    let cb_trait_ptr = Box::into_raw(cb_trait_box);
+    start_build(options, cb, None)
+}
 
-    let data = Box::into_raw(Box::new(BuildInvocationData {
-        opt_arc_raw: Arc::into_raw(options.clone()),
-        cb_trait_ptr: cb_trait_ptr as *mut c_void,
-    }));
+// Like `build_direct`, but passes any panic while completing the build to `on_panic`.
+pub(crate) fn start_build<F>(
+    options: Arc<BuildOptions>,
+    cb: F,
+    on_panic: Option<OnPanic>,
+) -> CancelHandle
+where
+    F: FnOnce(BuildResult),
+    F: Send + 'static,
+{
+    let cancel = CancelHandle::new();
+    let timeout = options.timeout;
+    let cb = cancel.timing_out(timeout, cb);
 
-    unsafe {
-        #[cfg(target_env = "msvc")]
-        #[allow(non_snake_case)]
-        let GoBuild = std::mem::transmute::<_, GoBuild>(crate::bridge::DLL.get_function("GoBuild"));
-
-        // We can safely convert anything in BuildOptions into raw pointers, as the memory is managed the the Arc and we only used owned values.
-        GoBuild(
-            libc::malloc,
-            build_callback,
-            data as *mut c_void,
-            options.ffiapi_ptr,
+    let started = cancel.clone();
+    limit::start(move || {
+        let data = new_build_invocation_data(
+            &options,
+            forwarding_panics(
+                on_panic,
+                limit::releasing(dispatching(&options.executor, cb)),
+            ),
         );
-    }
+        unsafe {
+            #[cfg(target_env = "msvc")]
+            #[allow(non_snake_case)]
+            let GoBuild =
+                std::mem::transmute::<_, GoBuild>(crate::bridge::DLL.get_function("GoBuild"));
+
+            // We can safely convert anything in BuildOptions into raw pointers, as the memory is managed the the Arc and we only used owned values.
+            GoBuild(
+                libc::malloc,
+                build_callback,
+                data,
+                options.ffiapi_ptr,
+                started.id(),
+            );
+        };
+        started.on_start();
+    });
+    // The timeout is measured from now, so time spent queued counts towards it.
+    cancel.cancel_after(timeout);
+
+    cancel
 }
 
 struct BuildFutureState {
     result: Option<BuildResult>,
+    // The result may have already been taken, so we track completion separately.
+    completed: bool,
+    // Set if the build was cancelled by `build_with_timeout`.
+    timed_out: bool,
+    // A panic while completing the build, to resume when polled.
+    panic: Option<PanicPayload>,
     waker: Option<Waker>,
 }
 
 pub struct BuildFuture {
     state: Arc<Mutex<BuildFutureState>>,
+    cancel: CancelHandle,
 }
 
-/// Future wrapper for `build_direct`.
+/// Future wrapper for `build_direct`. Dropping the Future before it completes cancels the build. If
+/// a panic occurs while completing the build, such as in the options' executor, it's resumed when
+/// the Future is polled.
 ///
 /// # Arguments
 ///
@@ -159,26 +238,75 @@ pub struct BuildFuture {
 /// }
 /// ```
 pub fn build(options: Arc<BuildOptions>) -> BuildFuture {
-    let state = Arc::new(Mutex::new(BuildFutureState {
-        result: None,
-        waker: None,
-    }));
-    let state_cb_copy = state.clone();
-    build_direct(options, move |result| {
-        let mut state = state_cb_copy.lock().unwrap();
-        state.result = Some(result);
-        if let Some(waker) = state.waker.take() {
-            waker.wake();
+    BuildFuture::new(|cb, on_panic| start_build(options, cb, Some(on_panic)))
+}
+
+impl BuildFuture {
+    // Creates a BuildFuture for the build started by `start` with the callback that completes it,
+    // and the callback to pass any panic while completing it to.
+    pub(crate) fn new<S>(start: S) -> BuildFuture
+    where
+        S: FnOnce(Box<dyn FnOnce(BuildResult) + Send>, OnPanic) -> CancelHandle,
+    {
+        let state = Arc::new(Mutex::new(BuildFutureState {
+            result: None,
+            completed: false,
+            timed_out: false,
+            panic: None,
+            waker: None,
+        }));
+        let state_cb_copy = state.clone();
+        let state_panic_copy = state.clone();
+        let cancel = start(
+            Box::new(move |result| {
+                let mut state = state_cb_copy.lock().unwrap();
+                state.result = Some(result);
+                state.completed = true;
+                if let Some(waker) = state.waker.take() {
+                    waker.wake();
+                };
+            }),
+            Box::new(move |payload| {
+                // The panic may have happened while the lock was held.
+                let mut state = state_panic_copy
+                    .lock()
+                    .unwrap_or_else(|err| err.into_inner());
+                state.panic = Some(payload);
+                state.completed = true;
+                if let Some(waker) = state.waker.take() {
+                    waker.wake();
+                };
+            }),
+        );
+        BuildFuture { state, cancel }
+    }
+
+    /// Cancels the build. The Future will still complete, with an error.
+    pub fn cancel(&self) {
+        self.cancel.cancel();
+    }
+}
+
+impl Drop for BuildFuture {
+    fn drop(&mut self) {
+        // Nothing will observe the result, so stop the Go work. The lock must be released first, as
+        // the callback needs it.
+        let completed = self.state.lock().map_or(true, |state| state.completed);
+        if !completed {
+            self.cancel.cancel();
         };
-    });
-    BuildFuture { state }
+    }
 }
 
 impl Future for BuildFuture {
     type Output = BuildResult;
 
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        let mut state = self.state.lock().unwrap();
+        let mut state = self.state.lock().unwrap_or_else(|err| err.into_inner());
+        if let Some(payload) = state.panic.take() {
+            drop(state);
+            panic::resume_unwind(payload);
+        };
         match state.result.take() {
             Some(result) => Poll::Ready(result),
             None => {
@@ -188,3 +316,149 @@ impl Future for BuildFuture {
         }
     }
 }
+
+/// Blocking wrapper for `build_direct`, which parks the calling thread until the build completes.
+/// If a panic occurs while completing the build, such as in the options' executor, it's resumed on
+/// the calling thread.
+///
+/// # Arguments
+///
+/// * `options` - Built BuildOptions created from a BuildOptionsBuilder.
+///
+/// # Examples
+///
+/// ```
+/// use esbuild_rs::{BuildOptionsBuilder, build_sync};
+///
+/// fn main() {
+///   let mut options_builder = BuildOptionsBuilder::new();
+///   options_builder.entry_points.push("index.js".to_string());
+///   let options = options_builder.build();
+///
+///   let res = build_sync(options);
+/// }
+/// ```
+pub fn build_sync(options: Arc<BuildOptions>) -> BuildResult {
+    blocking(|cb, on_panic| start_build(options, cb, Some(on_panic)))
+}
+
+pub struct TryBuildFuture {
+    inner: BuildFuture,
+}
+
+/// Like `build`, but resolves to an EsbuildError if the build had any errors.
+///
+/// # Arguments
+///
+/// * `options` - Built BuildOptions created from a BuildOptionsBuilder. A reference will be
+///   held on the Arc until the Future completes.
+///
+/// # Examples
+///
+/// This example uses the [async-std](https://crates.io/crates/async-std) async runtime.
+///
+/// ```
+/// use async_std::task;
+/// use esbuild_rs::{BuildOptionsBuilder, try_build, EsbuildError};
+///
+/// fn main() -> Result<(), EsbuildError> {
+///   let mut options_builder = BuildOptionsBuilder::new();
+///   options_builder.entry_points.push("index.js".to_string());
+///   let options = options_builder.build();
+///
+///   let res = task::block_on(try_build(options))?;
+///   Ok(())
+/// }
+/// ```
+pub fn try_build(options: Arc<BuildOptions>) -> TryBuildFuture {
+    TryBuildFuture {
+        inner: build(options),
+    }
+}
+
+impl TryBuildFuture {
+    /// Cancels the build. The Future will still complete, with an error.
+    pub fn cancel(&self) {
+        self.inner.cancel();
+    }
+}
+
+impl Future for TryBuildFuture {
+    type Output = Result<BuildResult, EsbuildError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.inner)
+            .poll(cx)
+            .map(BuildResult::into_result)
+    }
+}
+
+pub struct BuildTimeoutFuture {
+    inner: BuildFuture,
+    timeout: Duration,
+}
+
+/// Like `build`, but cancels the build and resolves to a TimeoutError if it doesn't complete within
+/// `timeout`. This is independent of any `timeout` set in the BuildOptions, which also resolves to
+/// a TimeoutError if exceeded.
+///
+/// # Arguments
+///
+/// * `options` - Built BuildOptions created from a BuildOptionsBuilder. A reference will be
+///   held on the Arc until the Future completes.
+/// * `timeout` - Maximum time to allow the build to run for.
+///
+/// # Examples
+///
+/// This example uses the [async-std](https://crates.io/crates/async-std) async runtime.
+///
+/// ```
+/// use std::time::Duration;
+/// use async_std::task;
+/// use esbuild_rs::{BuildOptionsBuilder, build_with_timeout};
+///
+/// fn main() {
+///   let mut options_builder = BuildOptionsBuilder::new();
+///   options_builder.entry_points.push("index.js".to_string());
+///   let options = options_builder.build();
+///
+///   match task::block_on(build_with_timeout(options, Duration::from_secs(10))) {
+///     Ok(res) => println!("Built with {} errors", res.errors.as_slice().len()),
+///     Err(err) => println!("{}", err),
+///   };
+/// }
+/// ```
+pub fn build_with_timeout(options: Arc<BuildOptions>, timeout: Duration) -> BuildTimeoutFuture {
+    let inner = build(options);
+    let state = Arc::downgrade(&inner.state);
+    let cancel = inner.cancel.clone();
+    timer::schedule(Instant::now() + timeout, move || {
+        // The Future may have already been dropped.
+        if let Some(state) = state.upgrade() {
+            let mut state = state.lock().unwrap_or_else(PoisonError::into_inner);
+            if !state.completed {
+                state.timed_out = true;
+                drop(state);
+                cancel.cancel();
+            };
+        };
+    });
+    BuildTimeoutFuture { inner, timeout }
+}
+
+impl Future for BuildTimeoutFuture {
+    type Output = Result<BuildResult, TimeoutError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let timeout = self.timeout;
+        Pin::new(&mut self.inner).poll(cx).map(|result| {
+            if self.inner.state.lock().unwrap().timed_out {
+                Err(TimeoutError { timeout })
+            } else if let Some(err) = result.timed_out {
+                Err(err)
+            } else {
+                Ok(result)
+            }
+        })
+    }
+}
@@ -0,0 +1,125 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use libc::size_t;
+
+use crate::bridge::GoCancel;
+use crate::error::TimeoutError;
+use crate::timer;
+use crate::wrapper::{BuildResult, Message, TransformResult};
+
+// The result of an operation that can be cancelled by its timeout.
+pub(crate) trait TimeoutResult {
+    fn errors(&self) -> &[Message];
+    fn set_timed_out(&mut self, timed_out: Option<TimeoutError>);
+}
+
+impl TimeoutResult for BuildResult {
+    fn errors(&self) -> &[Message] {
+        self.errors.as_slice()
+    }
+
+    fn set_timed_out(&mut self, timed_out: Option<TimeoutError>) {
+        self.timed_out = timed_out;
+    }
+}
+
+impl TimeoutResult for TransformResult {
+    fn errors(&self) -> &[Message] {
+        self.errors.as_slice()
+    }
+
+    fn set_timed_out(&mut self, timed_out: Option<TimeoutError>) {
+        self.timed_out = timed_out;
+    }
+}
+
+// IDs are never reused, so cancelling an operation that has already completed is harmless.
+static NEXT_CANCEL_ID: AtomicUsize = AtomicUsize::new(1);
+
+/// A handle for cancelling an in-progress build or transform. Cancelling stops the Go work as soon
+/// as possible; the callback is still called, with an error, so that all memory is freed.
+/// Cancelling an operation that has already completed does nothing.
+#[derive(Clone)]
+pub struct CancelHandle {
+    id: size_t,
+    // Go doesn't know about operations that haven't been started yet, so we remember cancellations
+    // to apply once they are.
+    cancelled: Arc<AtomicBool>,
+    // Set if the operation was cancelled by `cancel_after`.
+    timed_out: Arc<AtomicBool>,
+}
+
+impl CancelHandle {
+    pub(crate) fn new() -> CancelHandle {
+        CancelHandle {
+            id: NEXT_CANCEL_ID.fetch_add(1, Ordering::Relaxed),
+            cancelled: Arc::new(AtomicBool::new(false)),
+            timed_out: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub(crate) fn id(&self) -> size_t {
+        self.id
+    }
+
+    // Called once the operation has been started in Go, in case it was cancelled before then.
+    pub(crate) fn on_start(&self) {
+        if self.cancelled.load(Ordering::SeqCst) {
+            self.cancel();
+        };
+    }
+
+    // Cancels the operation if it hasn't completed within `timeout` from now, marking it as timed
+    // out. Does nothing if there's no timeout.
+    pub(crate) fn cancel_after(&self, timeout: Option<Duration>) {
+        if let Some(timeout) = timeout {
+            let cancel = self.clone();
+            timer::schedule(Instant::now() + timeout, move || {
+                cancel.timed_out.store(true, Ordering::SeqCst);
+                cancel.cancel();
+            });
+        };
+    }
+
+    // Returns the TimeoutError to report for a failed operation, if it was cancelled by
+    // `cancel_after`. An operation that completed successfully just as the timeout elapsed didn't
+    // time out.
+    pub(crate) fn timeout_error(
+        &self,
+        timeout: Option<Duration>,
+        errors: &[Message],
+    ) -> Option<TimeoutError> {
+        if errors.is_empty() || !self.timed_out.load(Ordering::SeqCst) {
+            return None;
+        };
+        timeout.map(|timeout| TimeoutError { timeout })
+    }
+
+    // Wraps `cb` to set `timed_out` on the result if the operation was cancelled by its timeout.
+    pub(crate) fn timing_out<R, F>(&self, timeout: Option<Duration>, cb: F) -> impl FnOnce(R)
+    where
+        R: TimeoutResult,
+        F: FnOnce(R),
+    {
+        let cancel = self.clone();
+        move |mut result: R| {
+            let timed_out = cancel.timeout_error(timeout, result.errors());
+            result.set_timed_out(timed_out);
+            cb(result)
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+        unsafe {
+            #[cfg(target_env = "msvc")]
+            #[allow(non_snake_case)]
+            let GoCancel =
+                std::mem::transmute::<_, GoCancel>(crate::bridge::DLL.get_function("GoCancel"));
+
+            GoCancel(self.id);
+        }
+    }
+}
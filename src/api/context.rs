@@ -0,0 +1,302 @@
+use std::os::raw::c_void;
+use std::sync::{Arc, Mutex};
+
+use libc::size_t;
+
+use crate::api::build::{build_callback, new_build_invocation_data, BuildFuture};
+use crate::api::cancel::CancelHandle;
+use crate::bridge::{
+    FfiapiMangleCacheEntry, GoBuildContext, GoBuildContextCancel, GoBuildContextDispose,
+    GoBuildContextRebuild, GoBuildContextWatch,
+};
+use crate::executor::{dispatching, Executor};
+use crate::panic::{catch_panic, forwarding_panics, OnPanic};
+use crate::wrapper::{
+    mangle_cache_from_ffiapi, BuildOptions, BuildResult, Message, OutputFile, SliceContainer,
+    StrContainer,
+};
+
+type OnRebuild = Arc<Mutex<Box<dyn FnMut(BuildResult) + Send>>>;
+
+struct WatchCallback {
+    // Shared with any calls still queued on the executor after watching has stopped.
+    on_rebuild: OnRebuild,
+    executor: Option<Arc<dyn Executor>>,
+}
+
+/// A handle to build state kept alive in Go, allowing the same build to be run repeatedly and
+/// incrementally. Created using `context`.
+///
+/// The Go-side state is released when the BuildContext is disposed or dropped.
+pub struct BuildContext {
+    // Go may refer to data in the options, such as plugin callbacks, until the context is disposed.
+    options: Arc<BuildOptions>,
+    id: size_t,
+}
+
+/// This function creates a BuildContext for running builds with the provided options. No build is
+/// run until `rebuild` is called. Subsequent rebuilds reuse the results of previous builds where
+/// possible, so only changed files need to be reparsed.
+///
+/// # Arguments
+///
+/// * `options` - Built BuildOptions created from a BuildOptionsBuilder. A reference will be held on
+///   the Arc until the BuildContext is dropped.
+///
+/// # Examples
+///
+/// This example uses the [async-std](https://crates.io/crates/async-std) async runtime.
+///
+/// ```
+/// use async_std::task;
+/// use esbuild_rs::{BuildOptionsBuilder, context};
+///
+/// fn main() {
+///   let mut options_builder = BuildOptionsBuilder::new();
+///   options_builder.entry_points.push("index.js".to_string());
+///   let options = options_builder.build();
+///
+///   let ctx = context(options);
+///   let first = task::block_on(ctx.rebuild());
+///   let second = task::block_on(ctx.rebuild());
+///   ctx.dispose();
+/// }
+/// ```
+pub fn context(options: Arc<BuildOptions>) -> BuildContext {
+    let id = unsafe {
+        #[cfg(target_env = "msvc")]
+        #[allow(non_snake_case)]
+        let GoBuildContext = std::mem::transmute::<_, GoBuildContext>(
+            crate::bridge::DLL.get_function("GoBuildContext"),
+        );
+
+        GoBuildContext(options.ffiapi_ptr)
+    };
+    BuildContext { options, id }
+}
+
+impl BuildContext {
+    /// Runs the build again, calling `cb` from a goroutine once it completes. This function returns
+    /// immediately; see `build_direct` for details.
+    pub fn rebuild_direct<F>(&self, cb: F) -> CancelHandle
+    where
+        F: FnOnce(BuildResult),
+        F: Send + 'static,
+    {
+        self.start_rebuild(cb, None)
+    }
+
+    // Like `rebuild_direct`, but passes any panic while completing the rebuild to `on_panic`.
+    fn start_rebuild<F>(&self, cb: F, on_panic: Option<OnPanic>) -> CancelHandle
+    where
+        F: FnOnce(BuildResult),
+        F: Send + 'static,
+    {
+        let cancel = CancelHandle::new();
+        let cb = cancel.timing_out(self.options.timeout, cb);
+        let data = new_build_invocation_data(
+            &self.options,
+            forwarding_panics(on_panic, dispatching(&self.options.executor, cb)),
+        );
+
+        unsafe {
+            #[cfg(target_env = "msvc")]
+            #[allow(non_snake_case)]
+            let GoBuildContextRebuild = std::mem::transmute::<_, GoBuildContextRebuild>(
+                crate::bridge::DLL.get_function("GoBuildContextRebuild"),
+            );
+
+            GoBuildContextRebuild(libc::malloc, build_callback, data, self.id, cancel.id());
+        };
+        // Each rebuild gets the full timeout.
+        cancel.cancel_after(self.options.timeout);
+
+        cancel
+    }
+
+    /// Future wrapper for `rebuild_direct`. Dropping the Future before it completes cancels the
+    /// rebuild.
+    pub fn rebuild(&self) -> BuildFuture {
+        BuildFuture::new(|cb, on_panic| self.start_rebuild(cb, Some(on_panic)))
+    }
+
+    /// Cancels any rebuild currently in progress. The cancelled rebuild still completes, with an
+    /// error, and the context can continue to be used.
+    pub fn cancel(&self) {
+        unsafe {
+            #[cfg(target_env = "msvc")]
+            #[allow(non_snake_case)]
+            let GoBuildContextCancel = std::mem::transmute::<_, GoBuildContextCancel>(
+                crate::bridge::DLL.get_function("GoBuildContextCancel"),
+            );
+
+            GoBuildContextCancel(self.id);
+        }
+    }
+
+    /// Releases the Go-side state. This is equivalent to dropping the BuildContext.
+    pub fn dispose(self) {
+        drop(self);
+    }
+}
+
+impl Drop for BuildContext {
+    fn drop(&mut self) {
+        unsafe {
+            #[cfg(target_env = "msvc")]
+            #[allow(non_snake_case)]
+            let GoBuildContextDispose = std::mem::transmute::<_, GoBuildContextDispose>(
+                crate::bridge::DLL.get_function("GoBuildContextDispose"),
+            );
+
+            GoBuildContextDispose(self.id);
+        }
+    }
+}
+
+extern "C" fn watch_callback(
+    raw_cb_data: *mut c_void,
+    metafile: StrContainer,
+    raw_output_files: *mut OutputFile,
+    output_files_len: size_t,
+    raw_errors: *mut Message,
+    errors_len: size_t,
+    raw_warnings: *mut Message,
+    warnings_len: size_t,
+    raw_mangle_cache: *mut FfiapiMangleCacheEntry,
+    mangle_cache_len: size_t,
+) {
+    unsafe {
+        // The callback is only borrowed, as it will be called again on the next change. It's
+        // dropped by WatchHandle once Go has stopped watching.
+        let cb = &*(raw_cb_data as *const WatchCallback);
+
+        let output_files = SliceContainer {
+            ptr: raw_output_files,
+            len: output_files_len,
+        };
+        let errors = SliceContainer {
+            ptr: raw_errors,
+            len: errors_len,
+        };
+        let warnings = SliceContainer {
+            ptr: raw_warnings,
+            len: warnings_len,
+        };
+        let mangle_cache = mangle_cache_from_ffiapi(SliceContainer {
+            ptr: raw_mangle_cache,
+            len: mangle_cache_len,
+        });
+
+        let result = BuildResult {
+            metafile,
+            output_files,
+            errors,
+            warnings,
+            mangle_cache,
+            timed_out: None,
+        };
+        let on_rebuild = cb.on_rebuild.clone();
+        // A panic can't unwind into Go, so it's discarded and the next rebuild is still reported,
+        // whether it happens on the Go thread or the executor. The Mutex is poisoned by the panic,
+        // but the callback can still be called.
+        let _ = catch_panic(|| {
+            dispatching(&cb.executor, move |result| {
+                let _ = catch_panic(|| {
+                    let mut on_rebuild = on_rebuild.lock().unwrap_or_else(|err| err.into_inner());
+                    on_rebuild(result)
+                });
+            })(result)
+        });
+    };
+}
+
+/// A handle to a running watch started by `watch`. Watching stops when the handle is stopped or
+/// dropped.
+pub struct WatchHandle {
+    ctx: Option<BuildContext>,
+    cb_ptr: *mut WatchCallback,
+}
+
+unsafe impl Send for WatchHandle {}
+
+unsafe impl Sync for WatchHandle {}
+
+/// This function runs a build, and then keeps watching the file system in Go for changes to any of
+/// the build's input files, rebuilding incrementally when one changes. `on_rebuild` is called with
+/// the result of the initial build and every subsequent rebuild, from a goroutine or the options'
+/// executor. Calls are never concurrent, but an executor may run them out of order.
+///
+/// # Arguments
+///
+/// * `options` - Built BuildOptions created from a BuildOptionsBuilder. A reference will be held on
+///   the Arc until the WatchHandle is dropped.
+/// * `on_rebuild` - Closure to call with the BuildResult after every build. A panic in it is
+///   reported by the panic hook and then discarded, and doesn't stop watching.
+///
+/// # Examples
+///
+/// ```
+/// use std::thread;
+/// use std::time::Duration;
+/// use esbuild_rs::{BuildOptionsBuilder, watch};
+///
+/// fn main() {
+///   let mut options_builder = BuildOptionsBuilder::new();
+///   options_builder.entry_points.push("index.js".to_string());
+///   options_builder.outdir = "dist".to_string();
+///   options_builder.write = true;
+///   let options = options_builder.build();
+///
+///   let handle = watch(options, |res| {
+///     println!("Rebuilt with {} errors", res.errors.as_slice().len());
+///   });
+///   thread::sleep(Duration::from_secs(1));
+///   handle.stop();
+/// }
+/// ```
+pub fn watch<F>(options: Arc<BuildOptions>, on_rebuild: F) -> WatchHandle
+where
+    F: FnMut(BuildResult),
+    F: Send + 'static,
+{
+    let cb_ptr = Box::into_raw(Box::new(WatchCallback {
+        on_rebuild: Arc::new(Mutex::new(Box::new(on_rebuild))),
+        executor: options.executor.clone(),
+    }));
+    let ctx = context(options);
+
+    unsafe {
+        #[cfg(target_env = "msvc")]
+        #[allow(non_snake_case)]
+        let GoBuildContextWatch = std::mem::transmute::<_, GoBuildContextWatch>(
+            crate::bridge::DLL.get_function("GoBuildContextWatch"),
+        );
+
+        GoBuildContextWatch(libc::malloc, watch_callback, cb_ptr as *mut c_void, ctx.id);
+    };
+
+    WatchHandle {
+        ctx: Some(ctx),
+        cb_ptr,
+    }
+}
+
+impl WatchHandle {
+    /// Stops watching and releases the Go-side state. This is equivalent to dropping the
+    /// WatchHandle.
+    pub fn stop(self) {
+        drop(self);
+    }
+}
+
+impl Drop for WatchHandle {
+    fn drop(&mut self) {
+        // Disposing of the context waits for any in-progress callback, after which Go will not
+        // call it again, so it's safe to drop it afterwards.
+        drop(self.ctx.take());
+        unsafe {
+            let _: Box<WatchCallback> = Box::from_raw(self.cb_ptr);
+        };
+    }
+}
@@ -0,0 +1,141 @@
+use std::cmp;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+type PendingStart = Box<dyn FnOnce() + Send>;
+
+struct Limiter {
+    limit: Option<usize>,
+    in_flight: usize,
+    queue: VecDeque<PendingStart>,
+}
+
+static LIMITER: Mutex<Limiter> = Mutex::new(Limiter {
+    limit: None,
+    in_flight: 0,
+    queue: VecDeque::new(),
+});
+
+/// Counts of builds and transforms subject to the concurrency limit, as returned by
+/// `concurrency_stats`.
+#[derive(Copy, Clone, Debug)]
+pub struct ConcurrencyStats {
+    pub limit: Option<usize>,
+    /// Operations that have been started in Go and haven't completed yet.
+    pub in_flight: usize,
+    /// Operations waiting for an in-flight operation to complete before they're started.
+    pub queued: usize,
+}
+
+impl Limiter {
+    fn has_capacity(&self) -> bool {
+        self.limit.is_none_or(|limit| self.in_flight < limit)
+    }
+
+    // Takes as many queued starts as the limit allows, counting them as in flight.
+    fn take_startable(&mut self) -> Vec<PendingStart> {
+        let mut startable = vec![];
+        while self.has_capacity() {
+            match self.queue.pop_front() {
+                Some(start) => {
+                    self.in_flight += 1;
+                    startable.push(start);
+                }
+                None => break,
+            };
+        }
+        startable
+    }
+}
+
+// Starts must be called without holding the lock, as they may complete synchronously and release.
+fn run_all(starts: Vec<PendingStart>) {
+    for start in starts {
+        start();
+    }
+}
+
+/// This function sets the maximum number of builds and transforms that can run in Go at once.
+/// Operations started beyond the limit are queued, and started in order as earlier operations
+/// complete; the direct functions still return immediately. By default there is no limit, so every
+/// operation spawns a goroutine straight away.
+///
+/// The limit applies to `build_direct` and `transform_direct`, as well as all functions that wrap
+/// them. Rebuilds of a BuildContext, watches, and servers aren't limited.
+///
+/// Queued operations can be cancelled, and will complete with an error once started. Lowering the
+/// limit doesn't affect operations already in flight.
+///
+/// # Arguments
+///
+/// * `limit` - Maximum number of operations to run at once, or None for no limit. A limit of 0 is
+///   treated as 1.
+///
+/// # Examples
+///
+/// ```
+/// use esbuild_rs::{concurrency_stats, set_concurrency_limit};
+///
+/// fn main() {
+///   set_concurrency_limit(Some(8));
+///   let stats = concurrency_stats();
+///   println!("{} in flight, {} queued", stats.in_flight, stats.queued);
+/// }
+/// ```
+pub fn set_concurrency_limit(limit: Option<usize>) {
+    let startable = {
+        let mut limiter = LIMITER.lock().unwrap();
+        limiter.limit = limit.map(|limit| cmp::max(limit, 1));
+        limiter.take_startable()
+    };
+    run_all(startable);
+}
+
+/// Returns the current concurrency limit and how many operations are in flight and queued.
+pub fn concurrency_stats() -> ConcurrencyStats {
+    let limiter = LIMITER.lock().unwrap();
+    ConcurrencyStats {
+        limit: limiter.limit,
+        in_flight: limiter.in_flight,
+        queued: limiter.queue.len(),
+    }
+}
+
+// Calls `start` now if the limit allows, otherwise queues it. The operation started must call
+// `release`, or the callback wrapped by `releasing`, exactly once when it completes.
+pub(crate) fn start<S>(start: S)
+where
+    S: FnOnce(),
+    S: Send + 'static,
+{
+    {
+        let mut limiter = LIMITER.lock().unwrap();
+        if !limiter.has_capacity() {
+            limiter.queue.push_back(Box::new(start));
+            return;
+        };
+        limiter.in_flight += 1;
+    };
+    start();
+}
+
+pub(crate) fn release() {
+    let startable = {
+        let mut limiter = LIMITER.lock().unwrap();
+        limiter.in_flight -= 1;
+        limiter.take_startable()
+    };
+    run_all(startable);
+}
+
+// Wraps the completion callback of an operation started using `start` to free its slot, starting
+// the next queued operations, before calling `cb`.
+pub(crate) fn releasing<T, F>(cb: F) -> impl FnOnce(T)
+where
+    F: FnOnce(T),
+{
+    move |result| {
+        release();
+        cb(result)
+    }
+}
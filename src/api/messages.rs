@@ -0,0 +1,540 @@
+use std::cmp;
+use std::fmt::Write;
+
+use crate::wrapper::{Location, Message};
+
+const BOLD: &str = "\x1b[1m";
+const DIM: &str = "\x1b[37m";
+const GREEN: &str = "\x1b[32m";
+const MAGENTA: &str = "\x1b[35m";
+const RED: &str = "\x1b[31m";
+const RESET: &str = "\x1b[0m";
+
+const ELLIPSIS: &str = "...";
+
+#[derive(Copy, Clone)]
+pub enum MessageKind {
+    Error,
+    Warning,
+}
+
+#[derive(Copy, Clone)]
+pub struct FormatOptions {
+    pub kind: MessageKind,
+    /// Whether to include ANSI escape codes for terminal colors.
+    pub color: bool,
+    /// Width to fit source lines within, or 0 for no limit.
+    pub terminal_width: usize,
+}
+
+struct Colors {
+    bold: &'static str,
+    dim: &'static str,
+    kind: &'static str,
+    marker: &'static str,
+    reset: &'static str,
+}
+
+impl Colors {
+    fn new(options: &FormatOptions) -> Colors {
+        if !options.color {
+            return Colors {
+                bold: "",
+                dim: "",
+                kind: "",
+                marker: "",
+                reset: "",
+            };
+        };
+        Colors {
+            bold: BOLD,
+            dim: DIM,
+            kind: match options.kind {
+                MessageKind::Error => RED,
+                MessageKind::Warning => MAGENTA,
+            },
+            marker: GREEN,
+            reset: RESET,
+        }
+    }
+}
+
+// A source line prepared for display, with the marked range in chars rather than bytes.
+struct SourceLine {
+    text: String,
+    marker_start: usize,
+    marker_end: usize,
+}
+
+impl SourceLine {
+    fn new(loc: &Location, width: usize) -> SourceLine {
+        // Tabs are replaced so that each char occupies exactly one column.
+        let line_text = loc.line_text.to_string_lossy().replace('\t', " ");
+        let chars = line_text.chars().collect::<Vec<char>>();
+        let column = cmp::max(loc.column, 0) as usize;
+        let length = cmp::max(loc.length, 0) as usize;
+        let marker_start = char_offset(&line_text, column);
+        let marker_end = cmp::max(char_offset(&line_text, column + length), marker_start);
+
+        if width == 0 || chars.len() <= width {
+            return SourceLine {
+                text: line_text,
+                marker_start,
+                marker_end,
+            };
+        };
+
+        // Show a window of the line centred on the marker, with ellipses where it's cut off.
+        let budget = cmp::max(width.saturating_sub(ELLIPSIS.len() * 2), 1);
+        let centre = (marker_start + cmp::min(marker_end, marker_start + budget)) / 2;
+        let end = cmp::min(centre.saturating_sub(budget / 2) + budget, chars.len());
+        let start = end.saturating_sub(budget);
+
+        let mut text = String::new();
+        if start > 0 {
+            text.push_str(ELLIPSIS);
+        };
+        let offset = text.len();
+        text.extend(&chars[start..end]);
+        if end < chars.len() {
+            text.push_str(ELLIPSIS);
+        };
+        let clamp = |i: usize| cmp::min(i.saturating_sub(start), end - start) + offset;
+        SourceLine {
+            text,
+            marker_start: clamp(marker_start),
+            marker_end: clamp(marker_end),
+        }
+    }
+}
+
+// Converts a byte offset into `s` to an offset in chars, clamped to the end of `s`.
+fn char_offset(s: &str, byte_offset: usize) -> usize {
+    s.char_indices()
+        .take_while(|(i, _)| *i < byte_offset)
+        .count()
+}
+
+fn write_location(out: &mut String, colors: &Colors, loc: &Location, options: &FormatOptions) {
+    let line_number = loc.line.to_string();
+    let margin = format!("    {} │ ", line_number);
+    // Even when the margin alone doesn't fit, keep at least one char rather than lifting the limit.
+    let width = match options.terminal_width {
+        0 => 0,
+        w => cmp::max(w.saturating_sub(margin.chars().count()), 1),
+    };
+    let source = SourceLine::new(loc, width);
+    let chars = source.text.chars().collect::<Vec<char>>();
+
+    let before = chars[..source.marker_start].iter().collect::<String>();
+    let marked = chars[source.marker_start..source.marker_end]
+        .iter()
+        .collect::<String>();
+    let after = chars[source.marker_end..].iter().collect::<String>();
+    writeln!(
+        out,
+        "{}{}{}{}{}{}{}{}",
+        colors.dim, margin, colors.reset, before, colors.marker, marked, colors.reset, after
+    )
+    .unwrap();
+
+    let suggestion = loc.suggestion.to_string_lossy();
+    let marker = if !suggestion.is_empty() {
+        suggestion.to_string()
+    } else if source.marker_end > source.marker_start {
+        "~".repeat(source.marker_end - source.marker_start)
+    } else {
+        "^".to_string()
+    };
+    writeln!(
+        out,
+        "{}    {} ╵ {}{}{}{}{}",
+        colors.dim,
+        " ".repeat(line_number.len()),
+        colors.reset,
+        " ".repeat(source.marker_start),
+        colors.marker,
+        marker,
+        colors.reset
+    )
+    .unwrap();
+}
+
+fn write_header(
+    out: &mut String,
+    colors: &Colors,
+    prefix: &str,
+    loc: Option<&Location>,
+    kind: &str,
+    kind_color: &str,
+    text: &str,
+) {
+    write!(out, "{}{}", colors.bold, prefix).unwrap();
+    if let Some(loc) = loc {
+        write!(out, "{}:{}:{}: ", loc.file, loc.line, loc.column).unwrap();
+    };
+    writeln!(
+        out,
+        "{}{}:{}{} {}{}",
+        kind_color, kind, colors.reset, colors.bold, text, colors.reset
+    )
+    .unwrap();
+}
+
+/// This function renders messages in the same style as esbuild's command-line output, including
+/// the offending source line with the relevant range underlined, and any attached notes.
+///
+/// # Arguments
+///
+/// * `messages` - Messages to render, such as the errors or warnings of a BuildResult.
+/// * `options` - Whether the messages are errors or warnings, and how to render them.
+///
+/// # Examples
+///
+/// This example uses the [async-std](https://crates.io/crates/async-std) async runtime.
+///
+/// ```
+/// use std::sync::Arc;
+/// use async_std::task;
+/// use esbuild_rs::{TransformOptionsBuilder, transform, format_messages, FormatOptions, MessageKind};
+///
+/// fn main() {
+///   let src = Arc::new(b"let x = ;".to_vec());
+///   let options = TransformOptionsBuilder::new().build();
+///
+///   let res = task::block_on(transform(src, options));
+///   for formatted in format_messages(res.errors.as_slice(), FormatOptions {
+///     kind: MessageKind::Error,
+///     color: false,
+///     terminal_width: 80,
+///   }) {
+///     eprint!("{}", formatted);
+///   }
+/// }
+/// ```
+///
+/// Source lines that don't fit within `terminal_width` are cut down to a window around the
+/// marked range, and messages without a location only have a header line.
+///
+/// ```
+/// use std::sync::Arc;
+/// use esbuild_rs::{BuildOptionsBuilder, build_sync, TransformOptionsBuilder, transform_sync, format_messages, FormatOptions, MessageKind};
+///
+/// fn main() {
+///   let format_options = FormatOptions {
+///     kind: MessageKind::Error,
+///     color: false,
+///     terminal_width: 40,
+///   };
+///
+///   let src = Arc::new(b"let x = ;".to_vec());
+///   let res = transform_sync(src, TransformOptionsBuilder::new().build());
+///   assert_eq!(format_messages(res.errors.as_slice(), format_options), vec![concat!(
+///     " > <stdin>:1:8: error: Unexpected \";\"\n",
+///     "    1 │ let x = ;\n",
+///     "      ╵         ~\n",
+///   )]);
+///
+///   let src = Arc::new(b"let total = (1 + 2 + 3 + 4 + 5 + 6 + 7 + 8 + 9 + 10 + ) + 11 + 12 + 13 + 14 + 15 + 16 + 17 + 18 + 19 + 20;".to_vec());
+///   let res = transform_sync(src, TransformOptionsBuilder::new().build());
+///   assert_eq!(format_messages(res.errors.as_slice(), format_options), vec![concat!(
+///     " > <stdin>:1:54: error: Unexpected \")\"\n",
+///     "    1 │ ...8 + 9 + 10 + ) + 11 + 12 +...\n",
+///     "      ╵                 ~\n",
+///   )]);
+///
+///   let mut options_builder = BuildOptionsBuilder::new();
+///   options_builder.entry_points.push("missing.js".to_string());
+///   let res = build_sync(options_builder.build());
+///   assert_eq!(format_messages(res.errors.as_slice(), format_options), vec![
+///     " > error: Could not resolve \"missing.js\"\n",
+///   ]);
+/// }
+/// ```
+pub fn format_messages(messages: &[Message], options: FormatOptions) -> Vec<String> {
+    let colors = Colors::new(&options);
+    let kind = match options.kind {
+        MessageKind::Error => "error",
+        MessageKind::Warning => "warning",
+    };
+
+    messages
+        .iter()
+        .map(|msg| {
+            let mut out = String::new();
+            let mut text = msg.text.to_string();
+            if !msg.plugin_name.as_bytes().is_empty() {
+                text = format!("[plugin {}] {}", msg.plugin_name, text);
+            };
+            let loc = msg.location.as_deref();
+            write_header(&mut out, &colors, " > ", loc, kind, colors.kind, &text);
+            if let Some(loc) = loc {
+                write_location(&mut out, &colors, loc, &options);
+            };
+
+            for note in msg.notes.as_slice() {
+                let loc = note.location.as_deref();
+                write_header(
+                    &mut out,
+                    &colors,
+                    "   ",
+                    loc,
+                    "note",
+                    colors.bold,
+                    &note.text.to_string_lossy(),
+                );
+                if let Some(loc) = loc {
+                    write_location(&mut out, &colors, loc, &options);
+                };
+            }
+
+            out
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wrapper::{BoxContainer, Note, SliceContainer, StrContainer};
+
+    fn s(text: &str) -> StrContainer {
+        StrContainer::from_bytes(text.as_bytes())
+    }
+
+    fn loc(line_text: &str, column: isize, length: isize) -> Location {
+        Location {
+            file: s("src/a.js"),
+            namespace: s("file"),
+            line: 12,
+            column,
+            length,
+            line_text: s(line_text),
+            suggestion: s(""),
+        }
+    }
+
+    fn location(line_text: &str, column: isize, length: isize) -> Option<BoxContainer<Location>> {
+        Some(BoxContainer::new(loc(line_text, column, length)))
+    }
+
+    fn message(text: &str, location: Option<BoxContainer<Location>>, notes: Vec<Note>) -> Message {
+        Message {
+            id: s(""),
+            plugin_name: s(""),
+            text: s(text),
+            location,
+            notes: SliceContainer::from_vec(notes),
+            detail: s(""),
+        }
+    }
+
+    fn format(msg: Message, terminal_width: usize) -> String {
+        let mut formatted = format_messages(
+            &[msg],
+            FormatOptions {
+                kind: MessageKind::Error,
+                color: false,
+                terminal_width,
+            },
+        );
+        assert_eq!(formatted.len(), 1);
+        formatted.remove(0)
+    }
+
+    #[test]
+    fn without_location() {
+        let msg = message("Could not resolve \"x\"", None, vec![]);
+        assert_eq!(format(msg, 80), " > error: Could not resolve \"x\"\n");
+    }
+
+    #[test]
+    fn with_location() {
+        let msg = message("Expected \";\"", location("let a = b c;", 10, 1), vec![]);
+        assert_eq!(
+            format(msg, 80),
+            concat!(
+                " > src/a.js:12:10: error: Expected \";\"\n",
+                "    12 │ let a = b c;\n",
+                "       ╵           ~\n",
+            )
+        );
+    }
+
+    #[test]
+    fn empty_range_uses_caret() {
+        let msg = message("Unexpected end of file", location("f(", 2, 0), vec![]);
+        assert_eq!(
+            format(msg, 0),
+            concat!(
+                " > src/a.js:12:2: error: Unexpected end of file\n",
+                "    12 │ f(\n",
+                "       ╵   ^\n",
+            )
+        );
+    }
+
+    #[test]
+    fn suggestion_replaces_marker() {
+        let loc = BoxContainer::new(Location {
+            suggestion: s("=="),
+            ..loc("if (a = b) {}", 6, 1)
+        });
+        let msg = message("Did you mean \"==\"?", Some(loc), vec![]);
+        assert_eq!(
+            format(msg, 80),
+            concat!(
+                " > src/a.js:12:6: error: Did you mean \"==\"?\n",
+                "    12 │ if (a = b) {}\n",
+                "       ╵       ==\n",
+            )
+        );
+    }
+
+    #[test]
+    fn tabs_and_multibyte_chars_take_one_column() {
+        // The column is in bytes, and "é" is two bytes.
+        let msg = message("Unexpected \"!\"", location("\té = !", 6, 1), vec![]);
+        assert_eq!(
+            format(msg, 80),
+            concat!(
+                " > src/a.js:12:6: error: Unexpected \"!\"\n",
+                "    12 │  é = !\n",
+                "       ╵      ~\n",
+            )
+        );
+    }
+
+    #[test]
+    fn notes_and_plugin_name() {
+        let note = Note {
+            text: s("The original declaration is here"),
+            location: location("let a = 1;", 4, 1),
+        };
+        let mut msg = message(
+            "Duplicate declaration \"a\"",
+            location("let a = 2;", 4, 1),
+            vec![
+                note,
+                Note {
+                    text: s("Rename one of them"),
+                    location: None,
+                },
+            ],
+        );
+        msg.plugin_name = s("lint");
+        assert_eq!(
+            format(msg, 80),
+            concat!(
+                " > src/a.js:12:4: error: [plugin lint] Duplicate declaration \"a\"\n",
+                "    12 │ let a = 2;\n",
+                "       ╵     ~\n",
+                "   src/a.js:12:4: note: The original declaration is here\n",
+                "    12 │ let a = 1;\n",
+                "       ╵     ~\n",
+                "   note: Rename one of them\n",
+            )
+        );
+    }
+
+    #[test]
+    fn line_that_fits_exactly_is_not_truncated() {
+        // The margin "    12 │ " takes 9 columns.
+        let msg = message("Unexpected \"b\"", location("a b c", 2, 1), vec![]);
+        assert_eq!(
+            format(msg, 14),
+            concat!(
+                " > src/a.js:12:2: error: Unexpected \"b\"\n",
+                "    12 │ a b c\n",
+                "       ╵   ~\n",
+            )
+        );
+    }
+
+    #[test]
+    fn truncates_both_sides_around_marker() {
+        let text = "0123456789abcdefghijklmnopqrstuvwxyz";
+        let msg = message("Unexpected \"i\"", location(text, 18, 1), vec![]);
+        assert_eq!(
+            format(msg, 9 + 16),
+            concat!(
+                " > src/a.js:12:18: error: Unexpected \"i\"\n",
+                "    12 │ ...defghijklm...\n",
+                "       ╵         ~\n",
+            )
+        );
+    }
+
+    #[test]
+    fn truncates_end_only_when_marker_is_near_start() {
+        let text = "0123456789abcdefghijklmnopqrstuvwxyz";
+        let msg = message("Unexpected \"1\"", location(text, 1, 1), vec![]);
+        assert_eq!(
+            format(msg, 9 + 16),
+            concat!(
+                " > src/a.js:12:1: error: Unexpected \"1\"\n",
+                "    12 │ 0123456789...\n",
+                "       ╵  ~\n",
+            )
+        );
+    }
+
+    #[test]
+    fn truncates_start_only_when_marker_is_near_end() {
+        let text = "0123456789abcdefghijklmnopqrstuvwxyz";
+        let msg = message("Unexpected \"y\"", location(text, 34, 1), vec![]);
+        assert_eq!(
+            format(msg, 9 + 16),
+            concat!(
+                " > src/a.js:12:34: error: Unexpected \"y\"\n",
+                "    12 │ ...qrstuvwxyz\n",
+                "       ╵            ~\n",
+            )
+        );
+    }
+
+    #[test]
+    fn long_range_is_clamped_to_window() {
+        let text = "0123456789abcdefghijklmnopqrstuvwxyz";
+        let msg = message("Too long", location(text, 4, 30), vec![]);
+        assert_eq!(
+            format(msg, 9 + 16),
+            concat!(
+                " > src/a.js:12:4: error: Too long\n",
+                "    12 │ ...456789abcd...\n",
+                "       ╵    ~~~~~~~~~~\n",
+            )
+        );
+    }
+
+    #[test]
+    fn narrow_terminal_keeps_one_char() {
+        let text = "0123456789";
+        let msg = message("Unexpected \"5\"", location(text, 5, 1), vec![]);
+        assert_eq!(
+            format(msg, 4),
+            concat!(
+                " > src/a.js:12:5: error: Unexpected \"5\"\n",
+                "    12 │ ...5...\n",
+                "       ╵    ~\n",
+            )
+        );
+    }
+
+    #[test]
+    fn color_codes_wrap_parts() {
+        let msg = message("Oops", None, vec![]);
+        let formatted = format_messages(
+            &[msg],
+            FormatOptions {
+                kind: MessageKind::Warning,
+                color: true,
+                terminal_width: 0,
+            },
+        );
+        assert_eq!(
+            formatted,
+            vec!["\x1b[1m > \x1b[35mwarning:\x1b[0m\x1b[1m Oops\x1b[0m\n"]
+        );
+    }
+}
@@ -1,2 +1,8 @@
 pub mod build;
+pub mod cancel;
+pub mod context;
+pub mod limit;
+pub mod messages;
+pub mod serve;
 pub mod transform;
+pub mod transform_many;
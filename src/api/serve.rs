@@ -0,0 +1,191 @@
+use std::mem::{ManuallyDrop, MaybeUninit};
+use std::os::raw::c_void;
+use std::sync::Arc;
+use std::time::Duration;
+
+use libc::{ptrdiff_t, size_t};
+
+use crate::bridge::{
+    FfiapiServeOptions, FfiapiServeResult, GoServe, GoServeStop, GoServeWait, GoString,
+};
+use crate::panic::catch_panic;
+use crate::wrapper::{BuildOptions, Message, SliceContainer, StrContainer};
+
+type OnRequestCallback = Arc<dyn Fn(&ServeOnRequestArgs) + Send + Sync>;
+
+// This is the ffiapi_serve_on_request_args struct in C; we declare it here to avoid having to needlessly rewrap in ServeOnRequestArgs.
+#[repr(C)]
+pub struct ServeOnRequestArgs {
+    pub remote_address: StrContainer,
+    pub method: StrContainer,
+    pub path: StrContainer,
+    pub status: ptrdiff_t,
+    pub time_in_ms: ptrdiff_t,
+}
+
+impl ServeOnRequestArgs {
+    /// How long it took to respond to the request.
+    pub fn duration(&self) -> Duration {
+        Duration::from_millis(self.time_in_ms as u64)
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct ServeOptions {
+    /// Port to listen on, or 0 to pick a free port.
+    pub port: u16,
+    /// Host to listen on, or empty to listen on all interfaces.
+    pub host: String,
+    /// Directory to serve files from when they aren't build outputs.
+    pub servedir: String,
+    /// Called from a goroutine after every request is handled. Panics are reported by the panic
+    /// hook and then discarded.
+    pub on_request: Option<OnRequestCallback>,
+}
+
+impl ServeOptions {
+    pub fn new() -> ServeOptions {
+        ServeOptions::default()
+    }
+}
+
+/// A handle to a running server started by `serve`. The server is stopped when the handle is
+/// stopped or dropped.
+pub struct ServeHandle {
+    pub host: StrContainer,
+    pub port: u16,
+    server: size_t,
+    // Go refers to the build options and on_request callback until the server has stopped.
+    _options: Arc<BuildOptions>,
+    _on_request: Option<Box<OnRequestCallback>>,
+}
+
+extern "C" fn serve_on_request_callback(cb_data: *mut c_void, args: ServeOnRequestArgs) {
+    unsafe {
+        let cb = &*(cb_data as *const OnRequestCallback);
+        // A panic can't unwind into Go, and shouldn't stop the server.
+        let _ = catch_panic(|| cb(&args));
+    };
+}
+
+/// This function starts esbuild's HTTP server, which runs a build for each incoming request and
+/// serves the latest build outputs from memory. The server runs in Go until stopped, and this
+/// function returns once it is listening.
+///
+/// # Arguments
+///
+/// * `serve_options` - Options for the HTTP server.
+/// * `options` - Built BuildOptions created from a BuildOptionsBuilder. A reference will be held on
+///   the Arc until the server stops.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+/// use esbuild_rs::{BuildOptionsBuilder, serve, ServeOptions};
+///
+/// fn main() {
+///   let mut options_builder = BuildOptionsBuilder::new();
+///   options_builder.entry_points.push("index.js".to_string());
+///   options_builder.bundle = true;
+///   let options = options_builder.build();
+///
+///   let mut serve_options = ServeOptions::new();
+///   serve_options.on_request = Some(Arc::new(|req| {
+///     println!("{} {} {} [{:?}]", req.method, req.path, req.status, req.duration());
+///   }));
+///
+///   match serve(serve_options, options) {
+///     Ok(server) => {
+///       println!("Serving on {}:{}", server.host, server.port);
+///       server.stop();
+///     }
+///     Err(errors) => panic!("Failed to start server"),
+///   };
+/// }
+/// ```
+pub fn serve(
+    serve_options: ServeOptions,
+    options: Arc<BuildOptions>,
+) -> Result<ServeHandle, SliceContainer<Message>> {
+    let on_request = serve_options.on_request.map(Box::new);
+    // Go only sets the fields it needs to, so start from a zeroed result rather than leaving the
+    // rest uninitialised.
+    let mut result = MaybeUninit::<FfiapiServeResult>::zeroed();
+
+    let result = unsafe {
+        #[cfg(target_env = "msvc")]
+        #[allow(non_snake_case)]
+        let GoServe = std::mem::transmute::<_, GoServe>(crate::bridge::DLL.get_function("GoServe"));
+
+        // The strings only need to live until GoServe returns, as Go copies them.
+        let ffiapi_serve_options = FfiapiServeOptions {
+            port: serve_options.port,
+            host: GoString::from_bytes_unmanaged(serve_options.host.as_bytes()),
+            servedir: GoString::from_bytes_unmanaged(serve_options.servedir.as_bytes()),
+            on_request: on_request.as_ref().map(|_| serve_on_request_callback as _),
+            on_request_data: match &on_request {
+                Some(cb) => cb.as_ref() as *const OnRequestCallback as *mut c_void,
+                None => std::ptr::null_mut(),
+            },
+        };
+        GoServe(
+            libc::malloc,
+            &ffiapi_serve_options,
+            options.ffiapi_ptr,
+            result.as_mut_ptr(),
+        );
+        result.assume_init()
+    };
+
+    let errors = SliceContainer {
+        ptr: result.errors,
+        len: result.errors_len,
+    };
+    if !errors.as_slice().is_empty() {
+        // `host` is left alone, as Go doesn't set it when the server fails to start.
+        return Err(errors);
+    };
+
+    Ok(ServeHandle {
+        host: ManuallyDrop::into_inner(result.host),
+        port: result.port,
+        server: result.server,
+        _options: options,
+        _on_request: on_request,
+    })
+}
+
+impl ServeHandle {
+    /// Blocks the current thread until the server stops.
+    pub fn wait(&self) {
+        unsafe {
+            #[cfg(target_env = "msvc")]
+            #[allow(non_snake_case)]
+            let GoServeWait = std::mem::transmute::<_, GoServeWait>(
+                crate::bridge::DLL.get_function("GoServeWait"),
+            );
+
+            GoServeWait(self.server);
+        }
+    }
+
+    /// Stops the server. This is equivalent to dropping the ServeHandle.
+    pub fn stop(self) {
+        drop(self);
+    }
+}
+
+impl Drop for ServeHandle {
+    fn drop(&mut self) {
+        unsafe {
+            #[cfg(target_env = "msvc")]
+            #[allow(non_snake_case)]
+            let GoServeStop = std::mem::transmute::<_, GoServeStop>(
+                crate::bridge::DLL.get_function("GoServeStop"),
+            );
+
+            GoServeStop(self.server);
+        }
+    }
+}
@@ -1,13 +1,24 @@
 use std::future::Future;
 use std::os::raw::c_void;
+use std::panic;
 use std::pin::Pin;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, PoisonError};
 use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
 
 use libc::size_t;
 
-use crate::bridge::{GoString, GoTransform};
-use crate::wrapper::{Message, SliceContainer, StrContainer, TransformOptions, TransformResult};
+use crate::api::cancel::CancelHandle;
+use crate::api::limit;
+use crate::bridge::{FfiapiMangleCacheEntry, FfiapiTransformOptions, GoString, GoTransform};
+use crate::error::{EsbuildError, TimeoutError};
+use crate::executor::dispatching;
+use crate::panic::{blocking, catch_panic, forwarding_panics, OnPanic, PanicPayload};
+use crate::timer;
+use crate::wrapper::{
+    mangle_cache_from_ffiapi, Message, SliceContainer, StrContainer, TransformOptions,
+    TransformResult,
+};
 
 struct TransformInvocationData {
     src_vec_arc_raw: Option<*const Vec<u8>>,
@@ -23,6 +34,8 @@ extern "C" fn transform_callback(
     errors_len: size_t,
     raw_warnings: *mut Message,
     warnings_len: size_t,
+    raw_mangle_cache: *mut FfiapiMangleCacheEntry,
+    mangle_cache_len: size_t,
 ) -> () {
     unsafe {
         let cb_data: Box<TransformInvocationData> = Box::from_raw(raw_cb_data as *mut _);
@@ -48,37 +61,105 @@ extern "C" fn transform_callback(
             ptr: raw_warnings,
             len: warnings_len,
         };
+        let mangle_cache = mangle_cache_from_ffiapi(SliceContainer {
+            ptr: raw_mangle_cache,
+            len: mangle_cache_len,
+        });
 
-        rust_cb_trait_box(TransformResult {
-            code,
-            map,
-            errors,
-            warnings,
+        // A panic can't unwind into Go. Panics are only left to reach here if nothing is waiting on
+        // the transform to re-raise them.
+        let _ = catch_panic(|| {
+            rust_cb_trait_box(TransformResult {
+                code,
+                map,
+                errors,
+                warnings,
+                mangle_cache,
+                timed_out: None,
+            })
         });
     };
 }
 
+// A transform that may be queued by the concurrency limit. The pointers stay valid until the
+// callback is called, as TransformInvocationData holds references on the Arcs, or the caller of
+// `transform_direct_unmanaged` has promised to keep the data alive.
+struct PendingTransform {
+    cb_data: *mut TransformInvocationData,
+    go_code: GoString,
+    options: *const FfiapiTransformOptions,
+}
+
+unsafe impl Send for PendingTransform {}
+
+impl PendingTransform {
+    unsafe fn start(self, cancel: &CancelHandle) {
+        #[cfg(target_env = "msvc")]
+        #[allow(non_snake_case)]
+        let GoTransform =
+            std::mem::transmute::<_, GoTransform>(crate::bridge::DLL.get_function("GoTransform"));
+
+        // We can safely convert anything in TransformOptions into raw pointers, as the memory is managed the the Arc and we only used owned values.
+        GoTransform(
+            libc::malloc,
+            transform_callback,
+            self.cb_data as *mut c_void,
+            self.go_code,
+            self.options,
+            cancel.id(),
+        );
+        cancel.on_start();
+    }
+}
+
+// The callback in `cb_data` must have been wrapped using `limit::releasing` and
+// `cancel.timing_out`.
 unsafe fn call_ffi_transform(
+    cancel: CancelHandle,
     cb_data: *mut TransformInvocationData,
     go_code: GoString,
     options: &TransformOptions,
-) -> () {
-    #[cfg(target_env = "msvc")]
-    #[allow(non_snake_case)]
-    let GoTransform =
-        std::mem::transmute::<_, GoTransform>(crate::bridge::DLL.get_function("GoTransform"));
-
-    // We can safely convert anything in TransformOptions into raw pointers, as the memory is managed the the Arc and we only used owned values.
-    GoTransform(
-        libc::malloc,
-        transform_callback,
-        cb_data as *mut c_void,
+) -> CancelHandle {
+    let pending = PendingTransform {
+        cb_data,
         go_code,
-        options.ffiapi_ptr,
-    );
+        options: options.ffiapi_ptr,
+    };
+    let started = cancel.clone();
+    limit::start(move || unsafe { pending.start(&started) });
+    // The timeout is measured from now, so time spent queued counts towards it.
+    cancel.cancel_after(options.timeout);
+
+    cancel
+}
+
+/// Like `transform_direct`, but borrows `code` and `options` instead of holding references on Arcs.
+/// As `cb` may not be Send, it's always called from the Go thread, even if the options have an
+/// executor.
+///
+/// # Safety
+///
+/// `code` and `options` must stay alive until `cb` is called. Prefer `transform_borrowed`, which
+/// enforces this by blocking until the transform completes.
+pub unsafe fn transform_direct_unmanaged<F>(
+    code: &[u8],
+    options: &TransformOptions,
+    cb: F,
+) -> CancelHandle
+where
+    F: FnOnce(TransformResult),
+{
+    start_transform_unmanaged(code, options, cb, None)
 }
 
-pub unsafe fn transform_direct_unmanaged<F>(code: &[u8], options: &TransformOptions, cb: F) -> ()
+// Like `transform_direct_unmanaged`, but passes any panic while completing the transform to
+// `on_panic`.
+unsafe fn start_transform_unmanaged<F>(
+    code: &[u8],
+    options: &TransformOptions,
+    cb: F,
+    on_panic: Option<OnPanic>,
+) -> CancelHandle
 where
     F: FnOnce(TransformResult),
 {
@@ -86,7 +167,10 @@ where
     let go_code = GoString::from_bytes_unmanaged(code);
 
     // Prepare callback.
-    let cb_box = Box::new(cb) as Box<dyn FnOnce(TransformResult)>;
+    let cancel = CancelHandle::new();
+    let cb = cancel.timing_out(options.timeout, cb);
+    let cb_box = Box::new(forwarding_panics(on_panic, limit::releasing(cb)))
+        as Box<dyn FnOnce(TransformResult)>;
     let cb_trait_box = Box::new(cb_box);
     let cb_trait_ptr = Box::into_raw(cb_trait_box);
 
@@ -96,7 +180,7 @@ where
         cb_trait_ptr: cb_trait_ptr as *mut c_void,
     }));
 
-    call_ffi_transform(data, go_code, options);
+    call_ffi_transform(cancel, data, go_code, options)
 }
 
 /// This function transforms a string of source code into JavaScript. It can be used to minify
@@ -114,7 +198,12 @@ where
 ///   the callback is asynchronously called from Go.
 /// * `options` - Built TransformOptions created from a TransformOptionsBuilder. A reference will be
 ///   held on the Arc until the callback is asynchronously called from Go.
-/// * `cb` - Closure to call once the goroutine completes with the TransformResult.
+/// * `cb` - Closure to call once the goroutine completes with the TransformResult, or to spawn on
+///   the options' executor if set. If it panics, the panic is reported by the panic hook and then
+///   discarded, as it can't unwind into Go and nothing is waiting on the transform to re-raise it.
+///
+/// Returns a CancelHandle that can be used to stop the transform early. If a concurrency limit has
+/// been set using `set_concurrency_limit`, the transform may be queued before it's started.
 ///
 /// # Examples
 ///
@@ -135,14 +224,32 @@ where
 ///
 ///   let wg = WaitGroup::new();
 ///   let task = wg.clone();
-///   transform_direct(src, options, |TransformResult { code, map, errors, warnings }| {
-///     assert_eq!(code.as_str(), "let x = world;\n");
+///   transform_direct(src, options, |TransformResult { code, map, errors, warnings, .. }| {
+///     assert_eq!(code.as_str().unwrap(), "let x = world;\n");
 ///     drop(task);
 ///   });
 ///   wg.wait();
 /// }
 /// ```
-pub fn transform_direct<F>(code: Arc<Vec<u8>>, options: Arc<TransformOptions>, cb: F) -> ()
+pub fn transform_direct<F>(
+    code: Arc<Vec<u8>>,
+    options: Arc<TransformOptions>,
+    cb: F,
+) -> CancelHandle
+where
+    F: FnOnce(TransformResult),
+    F: Send + 'static,
+{
+    start_transform(code, options, cb, None)
+}
+
+// Like `transform_direct`, but passes any panic while completing the transform to `on_panic`.
+pub(crate) fn start_transform<F>(
+    code: Arc<Vec<u8>>,
+    options: Arc<TransformOptions>,
+    cb: F,
+    on_panic: Option<OnPanic>,
+) -> CancelHandle
 where
     F: FnOnce(TransformResult),
     F: Send + 'static,
@@ -151,7 +258,12 @@ where
     let go_code = unsafe { GoString::from_bytes_unmanaged(&code) };
 
     // Prepare callback.
-    let cb_box = Box::new(cb) as Box<dyn FnOnce(TransformResult)>;
+    let cancel = CancelHandle::new();
+    let cb = cancel.timing_out(options.timeout, cb);
+    let cb_box = Box::new(forwarding_panics(
+        on_panic,
+        limit::releasing(dispatching(&options.executor, cb)),
+    )) as Box<dyn FnOnce(TransformResult)>;
     let cb_trait_box = Box::new(cb_box);
     let cb_trait_ptr = Box::into_raw(cb_trait_box);
 
@@ -161,21 +273,28 @@ where
         cb_trait_ptr: cb_trait_ptr as *mut c_void,
     }));
 
-    unsafe {
-        call_ffi_transform(data, go_code, options.as_ref());
-    };
+    unsafe { call_ffi_transform(cancel, data, go_code, options.as_ref()) }
 }
 
 struct TransformFutureState {
     result: Option<TransformResult>,
+    // The result may have already been taken, so we track completion separately.
+    completed: bool,
+    // Set if the transform was cancelled by `transform_with_timeout`.
+    timed_out: bool,
+    // A panic while completing the transform, to resume when polled.
+    panic: Option<PanicPayload>,
     waker: Option<Waker>,
 }
 
 pub struct TransformFuture {
     state: Arc<Mutex<TransformFutureState>>,
+    cancel: CancelHandle,
 }
 
-/// Future wrapper for `transform_direct`.
+/// Future wrapper for `transform_direct`. Dropping the Future before it completes cancels the
+/// transform. If a panic occurs while completing the transform, such as in the options' executor,
+/// it's resumed when the Future is polled.
 ///
 /// # Arguments
 ///
@@ -201,30 +320,79 @@ pub struct TransformFuture {
 ///   let options = options_builder.build();
 ///
 ///   let res = task::block_on(transform(src, options));
-///   assert_eq!(res.code.as_str(), "let x = world;\n");
+///   assert_eq!(res.code.as_str().unwrap(), "let x = world;\n");
 /// }
 /// ```
 pub fn transform(code: Arc<Vec<u8>>, options: Arc<TransformOptions>) -> TransformFuture {
-    let state = Arc::new(Mutex::new(TransformFutureState {
-        result: None,
-        waker: None,
-    }));
-    let state_cb_copy = state.clone();
-    transform_direct(code, options, move |result| {
-        let mut state = state_cb_copy.lock().unwrap();
-        state.result = Some(result);
-        if let Some(waker) = state.waker.take() {
-            waker.wake();
+    TransformFuture::new(|cb, on_panic| start_transform(code, options, cb, Some(on_panic)))
+}
+
+impl TransformFuture {
+    // Creates a TransformFuture for the transform started by `start` with the callback that
+    // completes it, and the callback to pass any panic while completing it to.
+    pub(crate) fn new<S>(start: S) -> TransformFuture
+    where
+        S: FnOnce(Box<dyn FnOnce(TransformResult) + Send>, OnPanic) -> CancelHandle,
+    {
+        let state = Arc::new(Mutex::new(TransformFutureState {
+            result: None,
+            completed: false,
+            timed_out: false,
+            panic: None,
+            waker: None,
+        }));
+        let state_cb_copy = state.clone();
+        let state_panic_copy = state.clone();
+        let cancel = start(
+            Box::new(move |result| {
+                let mut state = state_cb_copy.lock().unwrap();
+                state.result = Some(result);
+                state.completed = true;
+                if let Some(waker) = state.waker.take() {
+                    waker.wake();
+                };
+            }),
+            Box::new(move |payload| {
+                // The panic may have happened while the lock was held.
+                let mut state = state_panic_copy
+                    .lock()
+                    .unwrap_or_else(|err| err.into_inner());
+                state.panic = Some(payload);
+                state.completed = true;
+                if let Some(waker) = state.waker.take() {
+                    waker.wake();
+                };
+            }),
+        );
+        TransformFuture { state, cancel }
+    }
+
+    /// Cancels the transform. The Future will still complete, with an error.
+    pub fn cancel(&self) {
+        self.cancel.cancel();
+    }
+}
+
+impl Drop for TransformFuture {
+    fn drop(&mut self) {
+        // Nothing will observe the result, so stop the Go work. The lock must be released first, as
+        // the callback needs it.
+        let completed = self.state.lock().map_or(true, |state| state.completed);
+        if !completed {
+            self.cancel.cancel();
         };
-    });
-    TransformFuture { state }
+    }
 }
 
 impl Future for TransformFuture {
     type Output = TransformResult;
 
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        let mut state = self.state.lock().unwrap();
+        let mut state = self.state.lock().unwrap_or_else(|err| err.into_inner());
+        if let Some(payload) = state.panic.take() {
+            drop(state);
+            panic::resume_unwind(payload);
+        };
         match state.result.take() {
             Some(result) => Poll::Ready(result),
             None => {
@@ -234,3 +402,198 @@ impl Future for TransformFuture {
         }
     }
 }
+
+/// Blocking wrapper for `transform_direct`, which parks the calling thread until the transform
+/// completes. If a panic occurs while completing the transform, such as in the options' executor,
+/// it's resumed on the calling thread.
+///
+/// # Arguments
+///
+/// * `code` - Source code to transform. Must be UTF-8.
+/// * `options` - Built TransformOptions created from a TransformOptionsBuilder.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+/// use esbuild_rs::{TransformOptionsBuilder, transform_sync};
+///
+/// fn main() {
+///   let src = Arc::new(b"let x = NAME;".to_vec());
+///
+///   let mut options_builder = TransformOptionsBuilder::new();
+///   options_builder.define.insert("NAME".to_string(), "world".to_string());
+///   let options = options_builder.build();
+///
+///   let res = transform_sync(src, options);
+///   assert_eq!(res.code.as_str().unwrap(), "let x = world;\n");
+/// }
+/// ```
+pub fn transform_sync(code: Arc<Vec<u8>>, options: Arc<TransformOptions>) -> TransformResult {
+    blocking(|cb, on_panic| start_transform(code, options, cb, Some(on_panic)))
+}
+
+/// Blocking transform of borrowed source code, which parks the calling thread until the transform
+/// completes. Unlike `transform_sync`, this does not require copying `code` into an Arc.
+///
+/// # Arguments
+///
+/// * `code` - Source code to transform.
+/// * `options` - Built TransformOptions created from a TransformOptionsBuilder.
+///
+/// # Examples
+///
+/// ```
+/// use esbuild_rs::{TransformOptionsBuilder, transform_borrowed};
+///
+/// fn main() {
+///   let mut options_builder = TransformOptionsBuilder::new();
+///   options_builder.minify_whitespace = true;
+///   let options = options_builder.build();
+///
+///   for src in &["let x = 1;", "let y = 2;"] {
+///     let res = transform_borrowed(src, &options);
+///     println!("{}", res.code);
+///   }
+/// }
+/// ```
+pub fn transform_borrowed(code: &str, options: &TransformOptions) -> TransformResult {
+    blocking(|cb, on_panic| unsafe {
+        // We block until the callback is called, so the borrows outlive the transform.
+        start_transform_unmanaged(code.as_bytes(), options, cb, Some(on_panic))
+    })
+}
+
+pub struct TryTransformFuture {
+    inner: TransformFuture,
+}
+
+/// Like `transform`, but resolves to an EsbuildError if the transform had any errors.
+///
+/// # Arguments
+///
+/// * `code` - Source code to transform. Must be UTF-8. A reference will be held on the Arc until
+///   the Future completes.
+/// * `options` - Built TransformOptions created from a TransformOptionsBuilder. A reference will be
+///   held on the Arc until the Future completes.
+///
+/// # Examples
+///
+/// This example uses the [async-std](https://crates.io/crates/async-std) async runtime.
+///
+/// ```
+/// use std::sync::Arc;
+/// use async_std::task;
+/// use esbuild_rs::{TransformOptionsBuilder, try_transform, EsbuildError};
+///
+/// fn main() -> Result<(), EsbuildError> {
+///   let src = Arc::new(b"let x = NAME;".to_vec());
+///
+///   let mut options_builder = TransformOptionsBuilder::new();
+///   options_builder.define.insert("NAME".to_string(), "world".to_string());
+///   let options = options_builder.build();
+///
+///   let res = task::block_on(try_transform(src, options))?;
+///   assert_eq!(res.code.as_str().unwrap(), "let x = world;\n");
+///   Ok(())
+/// }
+/// ```
+pub fn try_transform(code: Arc<Vec<u8>>, options: Arc<TransformOptions>) -> TryTransformFuture {
+    TryTransformFuture {
+        inner: transform(code, options),
+    }
+}
+
+impl TryTransformFuture {
+    /// Cancels the transform. The Future will still complete, with an error.
+    pub fn cancel(&self) {
+        self.inner.cancel();
+    }
+}
+
+impl Future for TryTransformFuture {
+    type Output = Result<TransformResult, EsbuildError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.inner)
+            .poll(cx)
+            .map(TransformResult::into_result)
+    }
+}
+
+pub struct TransformTimeoutFuture {
+    inner: TransformFuture,
+    timeout: Duration,
+}
+
+/// Like `transform`, but cancels the transform and resolves to a TimeoutError if it doesn't
+/// complete within `timeout`. This is independent of any `timeout` set in the TransformOptions,
+/// which also resolves to a TimeoutError if exceeded.
+///
+/// # Arguments
+///
+/// * `code` - Source code to transform. Must be UTF-8. A reference will be held on the Arc until
+///   the Future completes.
+/// * `options` - Built TransformOptions created from a TransformOptionsBuilder. A reference will be
+///   held on the Arc until the Future completes.
+/// * `timeout` - Maximum time to allow the transform to run for.
+///
+/// # Examples
+///
+/// This example uses the [async-std](https://crates.io/crates/async-std) async runtime.
+///
+/// ```
+/// use std::sync::Arc;
+/// use std::time::Duration;
+/// use async_std::task;
+/// use esbuild_rs::{TransformOptionsBuilder, transform_with_timeout};
+///
+/// fn main() {
+///   let src = Arc::new(b"let x = NAME;".to_vec());
+///
+///   let mut options_builder = TransformOptionsBuilder::new();
+///   options_builder.define.insert("NAME".to_string(), "world".to_string());
+///   let options = options_builder.build();
+///
+///   let res = task::block_on(transform_with_timeout(src, options, Duration::from_secs(1))).unwrap();
+///   assert_eq!(res.code.as_str().unwrap(), "let x = world;\n");
+/// }
+/// ```
+pub fn transform_with_timeout(
+    code: Arc<Vec<u8>>,
+    options: Arc<TransformOptions>,
+    timeout: Duration,
+) -> TransformTimeoutFuture {
+    let inner = transform(code, options);
+    let state = Arc::downgrade(&inner.state);
+    let cancel = inner.cancel.clone();
+    timer::schedule(Instant::now() + timeout, move || {
+        // The Future may have already been dropped.
+        if let Some(state) = state.upgrade() {
+            let mut state = state.lock().unwrap_or_else(PoisonError::into_inner);
+            if !state.completed {
+                state.timed_out = true;
+                drop(state);
+                cancel.cancel();
+            };
+        };
+    });
+    TransformTimeoutFuture { inner, timeout }
+}
+
+impl Future for TransformTimeoutFuture {
+    type Output = Result<TransformResult, TimeoutError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let timeout = self.timeout;
+        Pin::new(&mut self.inner).poll(cx).map(|result| {
+            if self.inner.state.lock().unwrap().timed_out {
+                Err(TimeoutError { timeout })
+            } else if let Some(err) = result.timed_out {
+                Err(err)
+            } else {
+                Ok(result)
+            }
+        })
+    }
+}
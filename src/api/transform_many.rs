@@ -0,0 +1,404 @@
+use std::future::Future;
+use std::os::raw::c_void;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+use libc::size_t;
+
+use crate::api::cancel::CancelHandle;
+use crate::api::limit;
+use crate::bridge::{FfiapiTransformManyInput, FfiapiTransformResult, GoString, GoTransformMany};
+use crate::panic::{blocking, catch_panic, OnPanic, PanicPayload};
+use crate::wrapper::{SliceContainer, TransformOptions, TransformResult};
+
+pub type TransformManyInput = (Arc<Vec<u8>>, Arc<TransformOptions>);
+
+type OnResults = Box<dyn FnMut(usize, Vec<TransformResult>) + Send>;
+
+struct TransformManyInvocationData {
+    // Go refers to the code and options of every input until the last result has been received.
+    _inputs: Vec<TransformManyInput>,
+    ffiapi_inputs: Vec<FfiapiTransformManyInput>,
+    remaining: usize,
+    on_results: OnResults,
+}
+
+extern "C" fn transform_many_callback(
+    raw_cb_data: *mut c_void,
+    offset: size_t,
+    raw_results: *mut FfiapiTransformResult,
+    results_len: size_t,
+) {
+    unsafe {
+        let cb_data = raw_cb_data as *mut TransformManyInvocationData;
+        let results = SliceContainer {
+            ptr: raw_results,
+            len: results_len,
+        }
+        .into_iter()
+        .map(TransformResult::from_ffiapi)
+        .collect::<Vec<_>>();
+
+        let data = &mut *cb_data;
+        data.remaining -= results_len;
+        let done = data.remaining == 0;
+        if done {
+            limit::release();
+        };
+        // A panic can't unwind into Go. Panics are only left to reach here if nothing is waiting on
+        // the transforms to re-raise them.
+        let _ = catch_panic(|| (data.on_results)(offset, results));
+
+        if done {
+            let _: Box<TransformManyInvocationData> = Box::from_raw(cb_data);
+        };
+    };
+}
+
+// Calls `cb` once with all results, which are received at once without streaming.
+fn all_results<F>(cb: F) -> OnResults
+where
+    F: FnOnce(Vec<TransformResult>),
+    F: Send + 'static,
+{
+    let mut cb = Some(cb);
+    Box::new(move |_, results| {
+        if let Some(cb) = cb.take() {
+            cb(results);
+        };
+    })
+}
+
+// Starts the transforms, passing any panic while handling their results to `on_panic`.
+fn start_transform_many(
+    inputs: Vec<TransformManyInput>,
+    stream: bool,
+    mut on_results: OnResults,
+    on_panic: Option<OnPanic>,
+) -> CancelHandle {
+    let cancel = CancelHandle::new();
+    if inputs.is_empty() {
+        // Go would never call back, as there are no results.
+        on_results(0, vec![]);
+        return cancel;
+    };
+    let timeout = inputs.iter().filter_map(|(_, opt)| opt.timeout).min();
+    let timed_out = cancel.clone();
+    let mut on_panic = on_panic;
+    let on_results: OnResults = Box::new(move |offset, mut results: Vec<TransformResult>| {
+        for result in results.iter_mut() {
+            result.timed_out = timed_out.timeout_error(timeout, result.errors.as_slice());
+        }
+        let handled = panic::catch_unwind(AssertUnwindSafe(|| on_results(offset, results)));
+        if let Err(payload) = handled {
+            match on_panic.take() {
+                Some(on_panic) => on_panic(payload),
+                None => panic::resume_unwind(payload),
+            };
+        };
+    });
+
+    let started = cancel.clone();
+    limit::start(move || {
+        let ffiapi_inputs = inputs
+            .iter()
+            .map(|(code, opt)| FfiapiTransformManyInput {
+                code: unsafe { GoString::from_bytes_unmanaged(code) },
+                opt: opt.ffiapi_ptr,
+            })
+            .collect::<Vec<_>>();
+        let data = Box::into_raw(Box::new(TransformManyInvocationData {
+            remaining: inputs.len(),
+            _inputs: inputs,
+            ffiapi_inputs,
+            on_results,
+        }));
+
+        unsafe {
+            #[cfg(target_env = "msvc")]
+            #[allow(non_snake_case)]
+            let GoTransformMany = std::mem::transmute::<_, GoTransformMany>(
+                crate::bridge::DLL.get_function("GoTransformMany"),
+            );
+
+            // Moving the Vecs into the invocation data doesn't move their elements, so these
+            // pointers remain valid until it's dropped.
+            GoTransformMany(
+                libc::malloc,
+                transform_many_callback,
+                data as *mut c_void,
+                (*data).ffiapi_inputs.as_ptr(),
+                (*data).ffiapi_inputs.len(),
+                stream,
+                started.id(),
+            );
+        };
+        started.on_start();
+    });
+    // The timeout is measured from now, so time spent queued counts towards it.
+    cancel.cancel_after(timeout);
+
+    cancel
+}
+
+/// This function transforms many pieces of source code in a single call to Go, which runs them
+/// concurrently and returns all the results at once. This avoids the overhead of crossing the Cgo
+/// boundary for every input, which can dominate when transforming lots of small inputs.
+///
+/// Like `transform_direct`, this function returns immediately, and `cb` will be called from a
+/// goroutine once every transform has completed. The batch counts as one operation towards the
+/// concurrency limit, and the shortest `timeout` set in any of the options applies to the whole
+/// batch. Executors set in the options aren't used.
+///
+/// # Arguments
+///
+/// * `inputs` - Source code to transform with the TransformOptions to use for it. References will
+///   be held on the Arcs until the callback is asynchronously called from Go.
+/// * `cb` - Closure to call with the TransformResults, in the same order as `inputs`, once all
+///   transforms have completed.
+///
+/// Returns a CancelHandle that can be used to stop all transforms early.
+///
+/// # Examples
+///
+/// This example uses the [crossbeam](https://docs.rs/crossbeam/) crate to prevent Rust from exiting
+/// until the transforms complete.
+///
+/// ```
+/// use std::sync::Arc;
+/// use crossbeam::sync::WaitGroup;
+/// use esbuild_rs::{TransformOptionsBuilder, transform_many_direct};
+///
+/// fn main() {
+///   let mut options_builder = TransformOptionsBuilder::new();
+///   options_builder.minify_whitespace = true;
+///   let options = options_builder.build();
+///
+///   let inputs = vec![
+///     (Arc::new(b"let x = 1;".to_vec()), options.clone()),
+///     (Arc::new(b"let y = 2;".to_vec()), options.clone()),
+///   ];
+///
+///   let wg = WaitGroup::new();
+///   let task = wg.clone();
+///   transform_many_direct(inputs, |results| {
+///     for res in results {
+///       println!("{}", res.code);
+///     }
+///     drop(task);
+///   });
+///   wg.wait();
+/// }
+/// ```
+pub fn transform_many_direct<F>(inputs: Vec<TransformManyInput>, cb: F) -> CancelHandle
+where
+    F: FnOnce(Vec<TransformResult>),
+    F: Send + 'static,
+{
+    start_transform_many(inputs, false, all_results(cb), None)
+}
+
+/// Like `transform_many_direct`, but calls `on_result` from a goroutine with each TransformResult as
+/// soon as its transform completes, which may be out of order. Calls are never concurrent, and
+/// `on_result` is called exactly once for every input.
+///
+/// # Arguments
+///
+/// * `inputs` - Source code to transform with the TransformOptions to use for it. References will
+///   be held on the Arcs until all results have been received.
+/// * `on_result` - Closure to call with the index of an input and its TransformResult.
+///
+/// Returns a CancelHandle that can be used to stop all remaining transforms early.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+/// use std::sync::mpsc;
+/// use esbuild_rs::{TransformOptionsBuilder, transform_many_streaming};
+///
+/// fn main() {
+///   let options = TransformOptionsBuilder::new().build();
+///   let inputs = vec![
+///     (Arc::new(b"let x = 1;".to_vec()), options.clone()),
+///     (Arc::new(b"let y = 2;".to_vec()), options.clone()),
+///   ];
+///   let count = inputs.len();
+///
+///   let (sender, receiver) = mpsc::channel();
+///   transform_many_streaming(inputs, move |i, res| sender.send((i, res)).unwrap());
+///   for (i, res) in receiver.iter().take(count) {
+///     println!("Input {}: {}", i, res.code);
+///   }
+/// }
+/// ```
+pub fn transform_many_streaming<F>(
+    inputs: Vec<TransformManyInput>,
+    mut on_result: F,
+) -> CancelHandle
+where
+    F: FnMut(usize, TransformResult),
+    F: Send + 'static,
+{
+    start_transform_many(
+        inputs,
+        true,
+        Box::new(move |offset, results| {
+            for (i, result) in results.into_iter().enumerate() {
+                on_result(offset + i, result);
+            }
+        }),
+        None,
+    )
+}
+
+struct TransformManyFutureState {
+    results: Option<Vec<TransformResult>>,
+    // The results may have already been taken, so we track completion separately.
+    completed: bool,
+    // A panic while completing the transforms, to resume when polled.
+    panic: Option<PanicPayload>,
+    waker: Option<Waker>,
+}
+
+pub struct TransformManyFuture {
+    state: Arc<Mutex<TransformManyFutureState>>,
+    cancel: CancelHandle,
+}
+
+/// Future wrapper for `transform_many_direct`. Dropping the Future before it completes cancels the
+/// transforms. If a panic occurs while completing the transforms, it's resumed when the Future is
+/// polled.
+///
+/// # Arguments
+///
+/// * `inputs` - Source code to transform with the TransformOptions to use for it. References will
+///   be held on the Arcs until the Future completes.
+///
+/// # Examples
+///
+/// This example uses the [async-std](https://crates.io/crates/async-std) async runtime.
+///
+/// ```
+/// use std::sync::Arc;
+/// use async_std::task;
+/// use esbuild_rs::{TransformOptionsBuilder, transform_many};
+///
+/// fn main() {
+///   let mut options_builder = TransformOptionsBuilder::new();
+///   options_builder.define.insert("NAME".to_string(), "world".to_string());
+///   let options = options_builder.build();
+///
+///   let inputs = vec![
+///     (Arc::new(b"let x = NAME;".to_vec()), options.clone()),
+///     (Arc::new(b"let y = NAME;".to_vec()), options.clone()),
+///   ];
+///   let results = task::block_on(transform_many(inputs));
+///   assert_eq!(results[0].code.as_str().unwrap(), "let x = world;\n");
+///   assert_eq!(results[1].code.as_str().unwrap(), "let y = world;\n");
+/// }
+/// ```
+pub fn transform_many(inputs: Vec<TransformManyInput>) -> TransformManyFuture {
+    let state = Arc::new(Mutex::new(TransformManyFutureState {
+        results: None,
+        completed: false,
+        panic: None,
+        waker: None,
+    }));
+    let state_cb_copy = state.clone();
+    let state_panic_copy = state.clone();
+    let cancel = start_transform_many(
+        inputs,
+        false,
+        all_results(move |results| {
+            let mut state = state_cb_copy.lock().unwrap();
+            state.results = Some(results);
+            state.completed = true;
+            if let Some(waker) = state.waker.take() {
+                waker.wake();
+            };
+        }),
+        Some(Box::new(move |payload| {
+            // The panic may have happened while the lock was held.
+            let mut state = state_panic_copy
+                .lock()
+                .unwrap_or_else(|err| err.into_inner());
+            state.panic = Some(payload);
+            state.completed = true;
+            if let Some(waker) = state.waker.take() {
+                waker.wake();
+            };
+        })),
+    );
+    TransformManyFuture { state, cancel }
+}
+
+impl TransformManyFuture {
+    /// Cancels the transforms. The Future will still complete, with errors.
+    pub fn cancel(&self) {
+        self.cancel.cancel();
+    }
+}
+
+impl Drop for TransformManyFuture {
+    fn drop(&mut self) {
+        // Nothing will observe the results, so stop the Go work. The lock must be released first,
+        // as the callback needs it.
+        let completed = self.state.lock().map_or(true, |state| state.completed);
+        if !completed {
+            self.cancel.cancel();
+        };
+    }
+}
+
+impl Future for TransformManyFuture {
+    type Output = Vec<TransformResult>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.state.lock().unwrap_or_else(|err| err.into_inner());
+        if let Some(payload) = state.panic.take() {
+            drop(state);
+            panic::resume_unwind(payload);
+        };
+        match state.results.take() {
+            Some(results) => Poll::Ready(results),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// Blocking wrapper for `transform_many_direct`, which parks the calling thread until all
+/// transforms complete. If a panic occurs while completing the transforms, it's resumed on the
+/// calling thread.
+///
+/// # Arguments
+///
+/// * `inputs` - Source code to transform with the TransformOptions to use for it.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+/// use esbuild_rs::{TransformOptionsBuilder, transform_many_sync};
+///
+/// fn main() {
+///   let mut options_builder = TransformOptionsBuilder::new();
+///   options_builder.minify_whitespace = true;
+///   let options = options_builder.build();
+///
+///   let scripts = vec!["let x = 1;", "let y = 2;"];
+///   let results = transform_many_sync(scripts
+///     .iter()
+///     .map(|src| (Arc::new(src.as_bytes().to_vec()), options.clone()))
+///     .collect());
+///   assert_eq!(results.len(), scripts.len());
+/// }
+/// ```
+pub fn transform_many_sync(inputs: Vec<TransformManyInput>) -> Vec<TransformResult> {
+    blocking(|cb, on_panic| start_transform_many(inputs, false, all_results(cb), Some(on_panic)))
+}
@@ -1,9 +1,13 @@
-use std::mem;
+use std::mem::{self, ManuallyDrop};
 use std::os::raw::{c_char, c_void};
 
 use libc::{ptrdiff_t, size_t};
 
-use crate::wrapper::{Engine, EntryPoint, Loader, Message, OutputFile, StrContainer};
+use crate::api::serve::ServeOnRequestArgs;
+use crate::plugin::OnLoadArgs;
+use crate::wrapper::{
+    Engine, EntryPoint, Loader, Message, OutputFile, SliceContainer, StrContainer,
+};
 
 const DUMMY_SAFE_PTR: &[u8] = &[0u8; 1024];
 
@@ -101,6 +105,22 @@ impl FfiapiEngine {
     }
 }
 
+#[repr(C)]
+pub struct FfiapiSupportedEntry {
+    pub name: GoString,
+    pub supported: bool,
+}
+
+impl FfiapiSupportedEntry {
+    pub fn from_supported_entry(name: &'static str, supported: bool) -> FfiapiSupportedEntry {
+        FfiapiSupportedEntry {
+            // Feature names are static, so there's nothing to keep alive or leak.
+            name: unsafe { GoString::from_bytes_unmanaged(name.as_bytes()) },
+            supported,
+        }
+    }
+}
+
 #[repr(C)]
 pub struct FfiapiEntryPoint {
     pub input_path: GoString,
@@ -116,6 +136,14 @@ impl FfiapiEntryPoint {
     }
 }
 
+#[repr(C)]
+pub struct FfiapiStdinOptions {
+    pub contents: GoString,
+    pub resolve_dir: GoString,
+    pub sourcefile: GoString,
+    pub loader: u8,
+}
+
 #[repr(C)]
 pub struct FfiapiLoader {
     pub name: GoString,
@@ -131,6 +159,98 @@ impl FfiapiLoader {
     }
 }
 
+// This is the ffiapi_on_resolve_args struct in C. The kind is received as a plain u8, as Go may
+// send kinds from newer versions of esbuild that ResolveKind doesn't know about.
+#[repr(C)]
+pub struct FfiapiOnResolveArgs {
+    pub path: StrContainer,
+    pub importer: StrContainer,
+    pub namespace: StrContainer,
+    pub resolve_dir: StrContainer,
+    pub kind: u8,
+}
+
+// Results of plugin callbacks are allocated by Rust using `malloc` and freed by Go once copied.
+#[repr(C)]
+pub struct FfiapiOnResolveResult {
+    pub path: StrContainer,
+    pub external: bool,
+    pub namespace: StrContainer,
+    // Non-empty if the callback panicked, in which case the other fields are empty.
+    pub error: StrContainer,
+}
+
+#[repr(C)]
+pub struct FfiapiOnLoadResult {
+    pub contents: StrContainer,
+    pub resolve_dir: StrContainer,
+    pub loader: u8,
+    // Non-empty if the callback panicked, in which case the other fields are empty.
+    pub error: StrContainer,
+}
+
+// These return false if the callback did not handle the path, in which case `result` is left untouched.
+pub type OnResolveApiCallback = extern "C" fn(
+    cb_data: *mut c_void,
+    args: FfiapiOnResolveArgs,
+    result: *mut FfiapiOnResolveResult,
+) -> bool;
+
+pub type OnLoadApiCallback =
+    extern "C" fn(cb_data: *mut c_void, args: OnLoadArgs, result: *mut FfiapiOnLoadResult) -> bool;
+
+#[repr(C)]
+pub struct FfiapiOnResolve {
+    pub filter: GoString,
+    pub namespace: GoString,
+    pub cb: OnResolveApiCallback,
+    pub cb_data: *mut c_void,
+}
+
+#[repr(C)]
+pub struct FfiapiOnLoad {
+    pub filter: GoString,
+    pub namespace: GoString,
+    pub cb: OnLoadApiCallback,
+    pub cb_data: *mut c_void,
+}
+
+#[repr(C)]
+pub struct FfiapiPlugin {
+    pub name: GoString,
+    pub on_resolve: *const FfiapiOnResolve,
+    pub on_resolve_len: size_t,
+    pub on_load: *const FfiapiOnLoad,
+    pub on_load_len: size_t,
+}
+
+// These return false if the path doesn't exist in the VirtualFs, in which case the out parameters
+// are left untouched. Results are allocated by Rust using `malloc` and freed by Go once copied.
+pub type VirtualFsReadFileCallback =
+    extern "C" fn(cb_data: *mut c_void, path: StrContainer, contents: *mut StrContainer) -> bool;
+
+pub type VirtualFsReadDirCallback = extern "C" fn(
+    cb_data: *mut c_void,
+    path: StrContainer,
+    entries: *mut *mut FfiapiVirtualFsDirEntry,
+    entries_len: *mut size_t,
+) -> bool;
+
+#[repr(C)]
+pub struct FfiapiVirtualFsDirEntry {
+    pub name: StrContainer,
+    pub is_dir: bool,
+}
+
+#[repr(C)]
+pub struct FfiapiVirtualFs {
+    pub read_file: VirtualFsReadFileCallback,
+    pub read_dir: VirtualFsReadDirCallback,
+    pub cb_data: *mut c_void,
+    // Whether to fall back to the real file system for paths that don't exist in the VirtualFs.
+    pub layered: bool,
+}
+
 pub type Allocator = unsafe extern "C" fn(n: size_t) -> *mut c_void;
 
 pub type BuildApiCallback = extern "C" fn(
@@ -142,6 +262,8 @@ pub type BuildApiCallback = extern "C" fn(
     errors_len: size_t,
     warnings: *mut Message,
     warnings_len: size_t,
+    mangle_cache: *mut FfiapiMangleCacheEntry,
+    mangle_cache_len: size_t,
 ) -> ();
 
 pub type TransformApiCallback = extern "C" fn(
@@ -152,8 +274,53 @@ pub type TransformApiCallback = extern "C" fn(
     errors_len: size_t,
     warnings: *mut Message,
     warnings_len: size_t,
+    mangle_cache: *mut FfiapiMangleCacheEntry,
+    mangle_cache_len: size_t,
 ) -> ();
 
+// A mangle cache entry in a build or transform result. The strings are allocated by Go using
+// `malloc`, and freed by Rust.
+#[repr(C)]
+pub struct FfiapiMangleCacheEntry {
+    pub key: StrContainer,
+    // Empty if `reserved`.
+    pub value: StrContainer,
+    pub reserved: bool,
+}
+
+// A mangle cache entry in options. The strings are owned by the options, and Go only reads them.
+#[repr(C)]
+pub struct FfiapiMangleCacheOption {
+    pub key: GoString,
+    // Empty if `reserved`.
+    pub value: GoString,
+    pub reserved: bool,
+}
+
+#[repr(C)]
+pub struct FfiapiTransformResult {
+    pub code: StrContainer,
+    pub map: StrContainer,
+    pub errors: SliceContainer<Message>,
+    pub warnings: SliceContainer<Message>,
+    pub mangle_cache: SliceContainer<FfiapiMangleCacheEntry>,
+}
+
+// Go calls this with consecutive results, starting at the input at `offset`. Calls are never
+// concurrent, and there is exactly one result per input across all calls.
+pub type TransformManyApiCallback = extern "C" fn(
+    cb_data: *mut c_void,
+    offset: size_t,
+    results: *mut FfiapiTransformResult,
+    results_len: size_t,
+) -> ();
+
+#[repr(C)]
+pub struct FfiapiTransformManyInput {
+    pub code: GoString,
+    pub opt: *const FfiapiTransformOptions,
+}
+
 #[repr(C)]
 pub struct FfiapiBuildOptions {
     pub source_map: u8,
@@ -163,6 +330,9 @@ pub struct FfiapiBuildOptions {
     pub target: u8,
     pub engines: *const FfiapiEngine,
     pub engines_len: size_t,
+    // JS and CSS features merged into one list, like esbuild's `supported` map.
+    pub supported: *const FfiapiSupportedEntry,
+    pub supported_len: size_t,
 
     pub minify_whitespace: bool,
     pub minify_identifiers: bool,
@@ -175,12 +345,22 @@ pub struct FfiapiBuildOptions {
     pub jsx_mode: u8,
     pub jsx_factory: GoString,
     pub jsx_fragment: GoString,
+    pub jsx_import_source: GoString,
+    pub jsx_dev: bool,
 
     pub define: *const FfiapiMapStringStringEntry,
     pub define_len: size_t,
     // Slice of GoStrings.
     pub pure: FfiapiGoStringGoSlice,
     pub keep_names: bool,
+    pub mangle_props: GoString,
+    pub reserve_props: GoString,
+    pub mangle_quoted: bool,
+    pub mangle_cache: *const FfiapiMangleCacheOption,
+    pub mangle_cache_len: size_t,
+    pub drop: u8,
+    // Slice of GoStrings.
+    pub drop_labels: FfiapiGoStringGoSlice,
 
     pub global_name: GoString,
     pub bundle: bool,
@@ -224,10 +404,41 @@ pub struct FfiapiBuildOptions {
     pub entry_points: FfiapiGoStringGoSlice,
     pub entry_points_advanced: *const FfiapiEntryPoint,
     pub entry_points_advanced_len: size_t,
+    // NULL if not building from stdin.
+    pub stdin: *const FfiapiStdinOptions,
 
     pub write: bool,
     pub allow_overwrite: bool,
     pub incremental: bool,
+
+    pub plugins: *const FfiapiPlugin,
+    pub plugins_len: size_t,
+
+    // NULL if using the real file system.
+    pub fs: *const FfiapiVirtualFs,
+}
+
+pub type ServeOnRequestApiCallback = extern "C" fn(cb_data: *mut c_void, args: ServeOnRequestArgs);
+
+#[repr(C)]
+pub struct FfiapiServeOptions {
+    pub port: u16,
+    pub host: GoString,
+    pub servedir: GoString,
+    // Go won't call this if it's NULL.
+    pub on_request: Option<ServeOnRequestApiCallback>,
+    pub on_request_data: *mut c_void,
+}
+
+#[repr(C)]
+pub struct FfiapiServeResult {
+    pub server: size_t,
+    // Go may leave this unset if there are errors, so it must only be taken out on success.
+    pub host: ManuallyDrop<StrContainer>,
+    pub port: u16,
+    // If there are any errors, the server failed to start and the other fields should be ignored.
+    pub errors: *mut Message,
+    pub errors_len: size_t,
 }
 
 #[repr(C)]
@@ -241,6 +452,9 @@ pub struct FfiapiTransformOptions {
     pub global_name: GoString,
     pub engines: *const FfiapiEngine,
     pub engines_len: size_t,
+    // JS and CSS features merged into one list, like esbuild's `supported` map.
+    pub supported: *const FfiapiSupportedEntry,
+    pub supported_len: size_t,
 
     pub minify_whitespace: bool,
     pub minify_identifiers: bool,
@@ -253,6 +467,8 @@ pub struct FfiapiTransformOptions {
     pub jsx_mode: u8,
     pub jsx_factory: GoString,
     pub jsx_fragment: GoString,
+    pub jsx_import_source: GoString,
+    pub jsx_dev: bool,
 
     pub tsconfig_raw: GoString,
     pub footer: GoString,
@@ -263,6 +479,14 @@ pub struct FfiapiTransformOptions {
     // Slice of GoStrings.
     pub pure: FfiapiGoStringGoSlice,
     pub keep_names: bool,
+    pub mangle_props: GoString,
+    pub reserve_props: GoString,
+    pub mangle_quoted: bool,
+    pub mangle_cache: *const FfiapiMangleCacheOption,
+    pub mangle_cache_len: size_t,
+    pub drop: u8,
+    // Slice of GoStrings.
+    pub drop_labels: FfiapiGoStringGoSlice,
 
     pub source_file: GoString,
     pub loader: u8,
@@ -282,11 +506,11 @@ macro_rules! declare_ffi_fn {
         $(
             $argn:ident: $argt:ty,
         )*
-    )) => (
+    ) $(-> $ret:ty)?) => (
         extern "C" {
             pub fn $name (
                 $($argn: $argt,)*
-            );
+            ) $(-> $ret)?;
         }
     )
 }
@@ -297,18 +521,21 @@ macro_rules! declare_ffi_fn {
         $(
             $argn:ident: $argt:ty,
         )*
-    )) => (
+    ) $(-> $ret:ty)?) => (
         pub type $name = extern "C" fn (
             $($argn: $argt,)*
-        );
+        ) $(-> $ret)?;
     )
 }
 
+// Operations taking a `cancel_id` can be cancelled using GoCancel. Cancelled operations still call
+// `cb`, with an error.
 declare_ffi_fn!(GoBuild(
     alloc: Allocator,
     cb: BuildApiCallback,
     cb_data: *mut c_void,
     opt: *const FfiapiBuildOptions,
+    cancel_id: size_t,
 ));
 
 declare_ffi_fn!(GoTransform(
@@ -317,4 +544,70 @@ declare_ffi_fn!(GoTransform(
     cb_data: *mut c_void,
     code: GoString,
     opt: *const FfiapiTransformOptions,
+    cancel_id: size_t,
+));
+
+// If `stream` is false, `cb` is called once with all results; otherwise, it's called with each
+// result as soon as it's ready.
+declare_ffi_fn!(GoTransformMany(
+    alloc: Allocator,
+    cb: TransformManyApiCallback,
+    cb_data: *mut c_void,
+    inputs: *const FfiapiTransformManyInput,
+    inputs_len: size_t,
+    stream: bool,
+    cancel_id: size_t,
+));
+
+// This does nothing if the operation has already completed.
+declare_ffi_fn!(GoCancel(
+    cancel_id: size_t,
+));
+
+// Returns an ID identifying the context in subsequent calls.
+declare_ffi_fn!(GoBuildContext(
+    opt: *const FfiapiBuildOptions,
+) -> size_t);
+
+declare_ffi_fn!(GoBuildContextRebuild(
+    alloc: Allocator,
+    cb: BuildApiCallback,
+    cb_data: *mut c_void,
+    ctx: size_t,
+    cancel_id: size_t,
+));
+
+// Unlike other functions, `cb` will be called with the same `cb_data` for the initial build and
+// every rebuild triggered by a change, until the context is disposed.
+declare_ffi_fn!(GoBuildContextWatch(
+    alloc: Allocator,
+    cb: BuildApiCallback,
+    cb_data: *mut c_void,
+    ctx: size_t,
+));
+
+declare_ffi_fn!(GoBuildContextCancel(
+    ctx: size_t,
+));
+
+declare_ffi_fn!(GoBuildContextDispose(
+    ctx: size_t,
+));
+
+// This function returns once the server has started or failed to start.
+declare_ffi_fn!(GoServe(
+    alloc: Allocator,
+    serve_opt: *const FfiapiServeOptions,
+    opt: *const FfiapiBuildOptions,
+    result: *mut FfiapiServeResult,
+));
+
+// This function blocks until the server has stopped.
+declare_ffi_fn!(GoServeWait(
+    server: size_t,
+));
+
+// This function blocks until the server has stopped, and can be called more than once.
+declare_ffi_fn!(GoServeStop(
+    server: size_t,
 ));
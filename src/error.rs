@@ -0,0 +1,57 @@
+use std::error::Error;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+
+use crate::wrapper::{Message, SliceContainer};
+
+/// An error for a build or transform that failed, owning all the error and warning messages
+/// produced.
+#[derive(Debug)]
+pub struct EsbuildError {
+    pub errors: SliceContainer<Message>,
+    pub warnings: SliceContainer<Message>,
+    /// Set if it failed because it exceeded the `timeout` in its options.
+    pub timed_out: Option<TimeoutError>,
+}
+
+impl Display for EsbuildError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if let Some(timed_out) = &self.timed_out {
+            return Display::fmt(timed_out, f);
+        };
+        let errors = self.errors.as_slice();
+        write!(
+            f,
+            "esbuild failed with {} error{}",
+            errors.len(),
+            if errors.len() == 1 { "" } else { "s" }
+        )?;
+        for error in errors {
+            write!(f, "\n{}", error)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for EsbuildError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.timed_out
+            .as_ref()
+            .map(|err| err as &(dyn Error + 'static))
+    }
+}
+
+/// An error for a build or transform that was cancelled because it didn't complete in time.
+#[derive(Copy, Clone, Debug)]
+pub struct TimeoutError {
+    pub timeout: Duration,
+}
+
+impl Display for TimeoutError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "esbuild timed out after {:?}", self.timeout)
+    }
+}
+
+impl Error for TimeoutError {}
@@ -0,0 +1,109 @@
+use std::cmp;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+
+use crate::panic::catch_panic;
+
+pub type Task = Box<dyn FnOnce() + Send>;
+
+/// Runs the completion callbacks of builds and transforms, instead of the Go-owned thread that
+/// calls back into Rust. Set it using the `executor` field of BuildOptionsBuilder or
+/// TransformOptionsBuilder.
+///
+/// Doing heavy work in a callback on a Go thread, such as writing and compressing outputs, blocks
+/// that thread from running other goroutines, so it's better to hand such callbacks off.
+///
+/// # Examples
+///
+/// This example runs callbacks on the calling thread of `spawn`, which is the default behaviour.
+///
+/// ```
+/// use esbuild_rs::{Executor, Task};
+///
+/// struct Inline;
+///
+/// impl Executor for Inline {
+///   fn spawn(&self, task: Task) {
+///     task();
+///   }
+/// }
+/// ```
+pub trait Executor: Send + Sync {
+    fn spawn(&self, task: Task);
+}
+
+/// An Executor with a fixed number of dedicated threads, which run callbacks in the order they're
+/// spawned. The threads exit once the ThreadPool has been dropped and all spawned callbacks have
+/// run.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+/// use esbuild_rs::{BuildOptionsBuilder, build_sync, ThreadPool};
+///
+/// fn main() {
+///   let mut options_builder = BuildOptionsBuilder::new();
+///   options_builder.entry_points.push("index.js".to_string());
+///   options_builder.executor = Some(Arc::new(ThreadPool::new(4)));
+///   let options = options_builder.build();
+///
+///   let res = build_sync(options);
+/// }
+/// ```
+pub struct ThreadPool {
+    sender: mpsc::Sender<Task>,
+}
+
+impl ThreadPool {
+    /// Starts a ThreadPool with `threads` threads, or one thread if `threads` is 0.
+    pub fn new(threads: usize) -> ThreadPool {
+        let (sender, receiver) = mpsc::channel::<Task>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        for i in 0..cmp::max(threads, 1) {
+            let receiver = receiver.clone();
+            thread::Builder::new()
+                .name(format!("esbuild-rs-pool-{}", i))
+                .spawn(move || loop {
+                    // The lock must only be held while receiving, not while running the task.
+                    let task = receiver.lock().unwrap().recv();
+                    match task {
+                        // A panicking callback shouldn't take down the thread.
+                        Ok(task) => {
+                            let _ = catch_panic(task);
+                        }
+                        // The ThreadPool has been dropped.
+                        Err(_) => break,
+                    };
+                })
+                .expect("spawn thread pool thread");
+        }
+        ThreadPool { sender }
+    }
+}
+
+impl Executor for ThreadPool {
+    fn spawn(&self, task: Task) {
+        // The threads only exit once the sender is dropped, so this can't fail.
+        self.sender.send(task).unwrap();
+    }
+}
+
+// Wraps `cb` to run on `executor`, if there is one.
+pub(crate) fn dispatching<T, F>(
+    executor: &Option<Arc<dyn Executor>>,
+    cb: F,
+) -> Box<dyn FnOnce(T) + Send>
+where
+    T: Send + 'static,
+    F: FnOnce(T),
+    F: Send + 'static,
+{
+    match executor {
+        Some(executor) => {
+            let executor = executor.clone();
+            Box::new(move |result| executor.spawn(Box::new(move || cb(result))))
+        }
+        None => Box::new(cb),
+    }
+}
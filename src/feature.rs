@@ -0,0 +1,124 @@
+// Declares an enum of features, along with the names esbuild uses for them in its `supported` map.
+macro_rules! declare_features {
+    ($(#[$meta:meta])* $vis:vis enum $name:ident { $($variant:ident => $str:literal,)* }) => {
+        $(#[$meta])*
+        #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+        $vis enum $name {
+            $($variant,)*
+        }
+
+        impl $name {
+            /// The name of this feature in esbuild's `supported` option.
+            pub fn as_str(self) -> &'static str {
+                match self {
+                    $($name::$variant => $str,)*
+                }
+            }
+        }
+    };
+}
+
+declare_features! {
+    /// A JavaScript syntax feature, for overriding whether it's supported using the `supported`
+    /// field of BuildOptionsBuilder or TransformOptionsBuilder.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use esbuild_rs::{JsFeature, Target, TransformOptionsBuilder};
+    ///
+    /// let mut options_builder = TransformOptionsBuilder::new();
+    /// options_builder.target = Target::ES2020;
+    /// // The WebView supports BigInt literals, but not top-level await.
+    /// options_builder.supported.insert(JsFeature::Bigint, true);
+    /// options_builder.supported.insert(JsFeature::TopLevelAwait, false);
+    /// ```
+    pub enum JsFeature {
+        ArbitraryModuleNamespaceNames => "arbitrary-module-namespace-names",
+        ArraySpread => "array-spread",
+        Arrow => "arrow",
+        AsyncAwait => "async-await",
+        AsyncGenerator => "async-generator",
+        Bigint => "bigint",
+        Class => "class",
+        ClassField => "class-field",
+        ClassPrivateAccessor => "class-private-accessor",
+        ClassPrivateBrandCheck => "class-private-brand-check",
+        ClassPrivateField => "class-private-field",
+        ClassPrivateMethod => "class-private-method",
+        ClassPrivateStaticAccessor => "class-private-static-accessor",
+        ClassPrivateStaticField => "class-private-static-field",
+        ClassPrivateStaticMethod => "class-private-static-method",
+        ClassStaticBlocks => "class-static-blocks",
+        ClassStaticField => "class-static-field",
+        ConstAndLet => "const-and-let",
+        Decorators => "decorators",
+        DefaultArgument => "default-argument",
+        Destructuring => "destructuring",
+        DynamicImport => "dynamic-import",
+        ExponentOperator => "exponent-operator",
+        ExportStarAs => "export-star-as",
+        ForAwait => "for-await",
+        ForOf => "for-of",
+        FunctionNameConfigurable => "function-name-configurable",
+        FunctionOrClassPropertyAccess => "function-or-class-property-access",
+        Generator => "generator",
+        Hashbang => "hashbang",
+        ImportAssertions => "import-assertions",
+        ImportAttributes => "import-attributes",
+        ImportMeta => "import-meta",
+        InlineScript => "inline-script",
+        LogicalAssignment => "logical-assignment",
+        NestedRestBinding => "nested-rest-binding",
+        NewTarget => "new-target",
+        NodeColonPrefixImport => "node-colon-prefix-import",
+        NodeColonPrefixRequire => "node-colon-prefix-require",
+        NullishCoalescing => "nullish-coalescing",
+        ObjectAccessors => "object-accessors",
+        ObjectExtensions => "object-extensions",
+        ObjectRestSpread => "object-rest-spread",
+        OptionalCatchBinding => "optional-catch-binding",
+        OptionalChain => "optional-chain",
+        RegexpDotAllFlag => "regexp-dot-all-flag",
+        RegexpLookbehindAssertions => "regexp-lookbehind-assertions",
+        RegexpMatchIndices => "regexp-match-indices",
+        RegexpNamedCaptureGroups => "regexp-named-capture-groups",
+        RegexpSetNotation => "regexp-set-notation",
+        RegexpStickyAndUnicodeFlags => "regexp-sticky-and-unicode-flags",
+        RegexpUnicodePropertyEscapes => "regexp-unicode-property-escapes",
+        RestArgument => "rest-argument",
+        TemplateLiteral => "template-literal",
+        TopLevelAwait => "top-level-await",
+        TypeofExoticObjectIsObject => "typeof-exotic-object-is-object",
+        UnicodeEscapes => "unicode-escapes",
+        Using => "using",
+    }
+}
+
+declare_features! {
+    /// A CSS feature, for overriding whether it's supported using the `supported_css` field of
+    /// BuildOptionsBuilder or TransformOptionsBuilder.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use esbuild_rs::{CssFeature, TransformOptionsBuilder};
+    ///
+    /// let mut options_builder = TransformOptionsBuilder::new();
+    /// options_builder.supported_css.insert(CssFeature::Nesting, false);
+    /// ```
+    pub enum CssFeature {
+        ColorFunctions => "color-functions",
+        GradientDoublePosition => "gradient-double-position",
+        GradientInterpolation => "gradient-interpolation",
+        GradientMidpoints => "gradient-midpoints",
+        HexRgba => "hex-rgba",
+        Hwb => "hwb",
+        InlineStyle => "inline-style",
+        InsetProperty => "inset-property",
+        IsPseudoClass => "is-pseudo-class",
+        ModernRgbHsl => "modern-rgb-hsl",
+        Nesting => "nesting",
+        RebeccaPurple => "rebecca-purple",
+    }
+}
@@ -1,7 +1,31 @@
 mod api;
 mod bridge;
+mod error;
+mod executor;
+mod feature;
+#[cfg(feature = "serde")]
+mod metafile;
+mod panic;
+mod plugin;
+mod timer;
+#[cfg(feature = "tokio")]
+pub mod tokio;
+mod vfs;
 mod wrapper;
 
 pub use crate::api::build::*;
+pub use crate::api::cancel::*;
+pub use crate::api::context::*;
+pub use crate::api::limit::*;
+pub use crate::api::messages::*;
+pub use crate::api::serve::*;
 pub use crate::api::transform::*;
+pub use crate::api::transform_many::*;
+pub use crate::error::*;
+pub use crate::executor::*;
+pub use crate::feature::*;
+#[cfg(feature = "serde")]
+pub use crate::metafile::*;
+pub use crate::plugin::*;
+pub use crate::vfs::*;
 pub use crate::wrapper::*;
@@ -0,0 +1,98 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::wrapper::{BuildResult, Format};
+
+// These mirror the JSON structure of the metafile produced by esbuild when
+// `BuildOptionsBuilder::metafile` is enabled.
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Metafile {
+    pub inputs: HashMap<String, MetafileInput>,
+    pub outputs: HashMap<String, MetafileOutput>,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct MetafileInput {
+    pub bytes: u64,
+    pub imports: Vec<MetafileImport>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<Format>,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct MetafileImport {
+    pub path: String,
+    pub kind: MetafileImportKind,
+    #[serde(default)]
+    pub external: bool,
+}
+
+// Output imports have kinds that never reach plugins, such as `file-loader`, so this is separate
+// from ResolveKind.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MetafileImportKind {
+    EntryPoint,
+    ImportStatement,
+    RequireCall,
+    DynamicImport,
+    RequireResolve,
+    ImportRule,
+    ComposesFrom,
+    #[serde(rename = "url-token")]
+    URLToken,
+    FileLoader,
+    // A kind from a newer version of esbuild.
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MetafileOutput {
+    pub bytes: u64,
+    pub inputs: HashMap<String, MetafileOutputInput>,
+    pub imports: Vec<MetafileImport>,
+    pub exports: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entry_point: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub css_bundle: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MetafileOutputInput {
+    pub bytes_in_output: u64,
+}
+
+impl BuildResult {
+    /// Parses the metafile JSON into a Metafile. This will fail if `metafile` wasn't enabled in the
+    /// BuildOptions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use async_std::task;
+    /// use esbuild_rs::{BuildOptionsBuilder, build};
+    ///
+    /// fn main() {
+    ///   let mut options_builder = BuildOptionsBuilder::new();
+    ///   options_builder.entry_points.push("index.js".to_string());
+    ///   options_builder.bundle = true;
+    ///   options_builder.metafile = true;
+    ///   let options = options_builder.build();
+    ///
+    ///   let res = task::block_on(build(options));
+    ///   let metafile = res.parsed_metafile().unwrap();
+    ///   for (path, output) in metafile.outputs.iter() {
+    ///     println!("{} is {} bytes", path, output.bytes);
+    ///   }
+    /// }
+    /// ```
+    pub fn parsed_metafile(&self) -> serde_json::Result<Metafile> {
+        serde_json::from_slice(self.metafile.as_bytes())
+    }
+}
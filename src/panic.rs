@@ -0,0 +1,72 @@
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc;
+
+// Unwinding from a Rust callback into Go is undefined behaviour, so every callback called from Go
+// must catch panics from the closures it calls. Where something is waiting on the operation, such
+// as a Future or a blocking call, the panic is forwarded to it to be re-raised there. Panics in Go
+// can't be caught from Rust; ffiapi recovers them itself and reports them as error Messages.
+
+pub(crate) type PanicPayload = Box<dyn Any + Send>;
+
+pub(crate) type OnPanic = Box<dyn FnOnce(PanicPayload) + Send>;
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(msg) = payload.downcast_ref::<&str>() {
+        msg.to_string()
+    } else if let Some(msg) = payload.downcast_ref::<String>() {
+        msg.clone()
+    } else {
+        "Box<dyn Any>".to_string()
+    }
+}
+
+// Calls `f`, catching any panic and returning its message instead. The panic hook will have
+// already reported the panic by then.
+pub(crate) fn catch_panic<R, F>(f: F) -> Result<R, String>
+where
+    F: FnOnce() -> R,
+{
+    panic::catch_unwind(AssertUnwindSafe(f)).map_err(|payload| panic_message(payload.as_ref()))
+}
+
+// Wraps `cb` to pass any panic to `on_panic`, if there is one, instead of unwinding. Without one,
+// the panic continues to unwind, to be caught and discarded by the callback called from Go.
+pub(crate) fn forwarding_panics<T, F>(on_panic: Option<OnPanic>, cb: F) -> impl FnOnce(T)
+where
+    F: FnOnce(T),
+{
+    move |result| {
+        if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(|| cb(result))) {
+            match on_panic {
+                Some(on_panic) => on_panic(payload),
+                None => panic::resume_unwind(payload),
+            };
+        };
+    }
+}
+
+// Runs an operation on the calling thread until it completes. `start` is given the callback to
+// complete the operation with and where to forward panics, which are resumed on this thread.
+pub(crate) fn blocking<T, S, R>(start: S) -> T
+where
+    T: Send + 'static,
+    S: FnOnce(Box<dyn FnOnce(T) + Send>, OnPanic) -> R,
+{
+    let (sender, receiver) = mpsc::sync_channel(1);
+    let panic_sender = sender.clone();
+    start(
+        Box::new(move |result| {
+            // If a panic was already forwarded, the receiver may have gone away.
+            let _ = sender.send(Ok(result));
+        }),
+        Box::new(move |payload| {
+            // The result may have already been sent.
+            let _ = panic_sender.try_send(Err(payload));
+        }),
+    );
+    match receiver.recv().unwrap() {
+        Ok(result) => result,
+        Err(payload) => panic::resume_unwind(payload),
+    }
+}
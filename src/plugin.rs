@@ -0,0 +1,318 @@
+use std::os::raw::c_void;
+use std::ptr;
+
+use crate::bridge::{
+    get_allocation_pointer, FfiapiOnLoad, FfiapiOnLoadResult, FfiapiOnResolve, FfiapiOnResolveArgs,
+    FfiapiOnResolveResult, FfiapiPlugin, GoString,
+};
+use crate::panic::catch_panic;
+use crate::wrapper::{Loader, StrContainer};
+
+type OnResolveCallback = Box<dyn Fn(&OnResolveArgs) -> Option<OnResolveResult> + Send + Sync>;
+type OnLoadCallback = Box<dyn Fn(&OnLoadArgs) -> Option<OnLoadResult> + Send + Sync>;
+
+// These are the values of the ffiapi_resolve_kind enum in C.
+#[derive(Copy, Clone)]
+pub enum ResolveKind {
+    EntryPoint,
+    ImportStatement,
+    RequireCall,
+    DynamicImport,
+    RequireResolve,
+    ImportRule,
+    URLToken,
+    // A kind from a newer version of esbuild.
+    Unknown,
+}
+
+impl ResolveKind {
+    fn from_ffiapi(kind: u8) -> ResolveKind {
+        match kind {
+            0 => ResolveKind::EntryPoint,
+            1 => ResolveKind::ImportStatement,
+            2 => ResolveKind::RequireCall,
+            3 => ResolveKind::DynamicImport,
+            4 => ResolveKind::RequireResolve,
+            5 => ResolveKind::ImportRule,
+            6 => ResolveKind::URLToken,
+            _ => ResolveKind::Unknown,
+        }
+    }
+}
+
+pub struct OnResolveArgs {
+    pub path: StrContainer,
+    pub importer: StrContainer,
+    pub namespace: StrContainer,
+    pub resolve_dir: StrContainer,
+    pub kind: ResolveKind,
+}
+
+// This is the ffiapi_on_load_args struct in C; we declare it here to avoid having to needlessly rewrap in OnLoadArgs.
+#[repr(C)]
+pub struct OnLoadArgs {
+    pub path: StrContainer,
+    pub namespace: StrContainer,
+}
+
+pub struct OnResolveResult {
+    pub path: String,
+    pub external: bool,
+    pub namespace: String,
+}
+
+pub struct OnLoadResult {
+    pub contents: Vec<u8>,
+    pub resolve_dir: String,
+    pub loader: Loader,
+}
+
+/// A plugin that hooks into module resolution and loading during a build. Plugins are added to
+/// `BuildOptionsBuilder::plugins`, and `setup` is called once when the BuildOptions are built to
+/// register callbacks.
+///
+/// Callbacks are called from goroutines while a build is running, possibly concurrently, so they
+/// must be thread safe and should avoid blocking for long periods. If a callback panics, the build
+/// fails with an error for the plugin instead.
+///
+/// # Examples
+///
+/// This example serves a virtual module for any import of `env`.
+///
+/// ```
+/// use std::sync::Arc;
+/// use async_std::task;
+/// use esbuild_rs::{BuildOptionsBuilder, build, Loader, OnLoadResult, OnResolveResult, Plugin, PluginBuild};
+///
+/// struct EnvPlugin;
+///
+/// impl Plugin for EnvPlugin {
+///   fn name(&self) -> &str {
+///     "env"
+///   }
+///
+///   fn setup(&self, build: &mut PluginBuild) {
+///     build.on_resolve("^env$", "", |args| Some(OnResolveResult {
+///       path: args.path.to_string(),
+///       external: false,
+///       namespace: "env-ns".to_string(),
+///     }));
+///     build.on_load(".*", "env-ns", |_| Some(OnLoadResult {
+///       contents: br#"{"NODE_ENV":"production"}"#.to_vec(),
+///       resolve_dir: "".to_string(),
+///       loader: Loader::JSON,
+///     }));
+///   }
+/// }
+///
+/// fn main() {
+///   let mut options_builder = BuildOptionsBuilder::new();
+///   options_builder.entry_points.push("index.js".to_string());
+///   options_builder.bundle = true;
+///   options_builder.plugins.push(Arc::new(EnvPlugin));
+///   let options = options_builder.build();
+///
+///   let res = task::block_on(build(options));
+/// }
+/// ```
+pub trait Plugin: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn setup(&self, build: &mut PluginBuild);
+}
+
+struct OnResolveRegistration {
+    filter: String,
+    namespace: String,
+    cb: OnResolveCallback,
+}
+
+struct OnLoadRegistration {
+    filter: String,
+    namespace: String,
+    cb: OnLoadCallback,
+}
+
+pub struct PluginBuild {
+    on_resolve: Vec<OnResolveRegistration>,
+    on_load: Vec<OnLoadRegistration>,
+}
+
+impl PluginBuild {
+    /// Registers a callback to run when esbuild resolves an import path matching `filter` (a Go
+    /// regular expression) in `namespace` (or any namespace if empty). Returning None passes the
+    /// path on to the next callback or esbuild's default resolution.
+    pub fn on_resolve<F>(&mut self, filter: &str, namespace: &str, cb: F)
+    where
+        F: Fn(&OnResolveArgs) -> Option<OnResolveResult>,
+        F: Send + Sync + 'static,
+    {
+        self.on_resolve.push(OnResolveRegistration {
+            filter: filter.to_string(),
+            namespace: namespace.to_string(),
+            cb: Box::new(cb),
+        });
+    }
+
+    /// Registers a callback to run when esbuild loads a resolved path matching `filter` (a Go
+    /// regular expression) in `namespace` (or any namespace if empty). Returning None passes the
+    /// path on to the next callback or esbuild's default loading from the file system.
+    pub fn on_load<F>(&mut self, filter: &str, namespace: &str, cb: F)
+    where
+        F: Fn(&OnLoadArgs) -> Option<OnLoadResult>,
+        F: Send + Sync + 'static,
+    {
+        self.on_load.push(OnLoadRegistration {
+            filter: filter.to_string(),
+            namespace: namespace.to_string(),
+            cb: Box::new(cb),
+        });
+    }
+}
+
+extern "C" fn on_resolve_callback(
+    cb_data: *mut c_void,
+    args: FfiapiOnResolveArgs,
+    result: *mut FfiapiOnResolveResult,
+) -> bool {
+    unsafe {
+        let cb = &*(cb_data as *const OnResolveCallback);
+        let args = OnResolveArgs {
+            path: args.path,
+            importer: args.importer,
+            namespace: args.namespace,
+            resolve_dir: args.resolve_dir,
+            kind: ResolveKind::from_ffiapi(args.kind),
+        };
+        match catch_panic(|| cb(&args)) {
+            Ok(Some(res)) => {
+                ptr::write(
+                    result,
+                    FfiapiOnResolveResult {
+                        path: StrContainer::from_bytes(res.path.as_bytes()),
+                        external: res.external,
+                        namespace: StrContainer::from_bytes(res.namespace.as_bytes()),
+                        error: StrContainer::from_bytes(b""),
+                    },
+                );
+                true
+            }
+            Ok(None) => false,
+            Err(msg) => {
+                ptr::write(
+                    result,
+                    FfiapiOnResolveResult {
+                        path: StrContainer::from_bytes(b""),
+                        external: false,
+                        namespace: StrContainer::from_bytes(b""),
+                        error: StrContainer::from_bytes(format!("panicked: {}", msg).as_bytes()),
+                    },
+                );
+                true
+            }
+        }
+    }
+}
+
+extern "C" fn on_load_callback(
+    cb_data: *mut c_void,
+    args: OnLoadArgs,
+    result: *mut FfiapiOnLoadResult,
+) -> bool {
+    unsafe {
+        let cb = &*(cb_data as *const OnLoadCallback);
+        match catch_panic(|| cb(&args)) {
+            Ok(Some(res)) => {
+                ptr::write(
+                    result,
+                    FfiapiOnLoadResult {
+                        contents: StrContainer::from_bytes(&res.contents),
+                        resolve_dir: StrContainer::from_bytes(res.resolve_dir.as_bytes()),
+                        loader: res.loader as u8,
+                        error: StrContainer::from_bytes(b""),
+                    },
+                );
+                true
+            }
+            Ok(None) => false,
+            Err(msg) => {
+                ptr::write(
+                    result,
+                    FfiapiOnLoadResult {
+                        contents: StrContainer::from_bytes(b""),
+                        resolve_dir: StrContainer::from_bytes(b""),
+                        loader: Loader::None as u8,
+                        error: StrContainer::from_bytes(format!("panicked: {}", msg).as_bytes()),
+                    },
+                );
+                true
+            }
+        }
+    }
+}
+
+// Holds a plugin's name and registered callbacks for as long as the BuildOptions using them live.
+pub(crate) struct PluginRegistration {
+    name: String,
+    // We keep the callbacks that cb_data fields in ffiapi_on_resolve and ffiapi_on_load point to.
+    _on_resolve: Vec<OnResolveRegistration>,
+    _on_load: Vec<OnLoadRegistration>,
+    ffiapi_on_resolve: Vec<FfiapiOnResolve>,
+    ffiapi_on_load: Vec<FfiapiOnLoad>,
+}
+
+impl PluginRegistration {
+    pub(crate) fn from_plugin(plugin: &dyn Plugin) -> PluginRegistration {
+        let mut build = PluginBuild {
+            on_resolve: vec![],
+            on_load: vec![],
+        };
+        plugin.setup(&mut build);
+
+        // Moving the Vecs into PluginRegistration doesn't move their elements, so these pointers
+        // remain valid.
+        let ffiapi_on_resolve = build
+            .on_resolve
+            .iter()
+            .map(|r| unsafe {
+                FfiapiOnResolve {
+                    filter: GoString::from_bytes_unmanaged(r.filter.as_bytes()),
+                    namespace: GoString::from_bytes_unmanaged(r.namespace.as_bytes()),
+                    cb: on_resolve_callback,
+                    cb_data: &r.cb as *const OnResolveCallback as *mut c_void,
+                }
+            })
+            .collect();
+        let ffiapi_on_load = build
+            .on_load
+            .iter()
+            .map(|r| unsafe {
+                FfiapiOnLoad {
+                    filter: GoString::from_bytes_unmanaged(r.filter.as_bytes()),
+                    namespace: GoString::from_bytes_unmanaged(r.namespace.as_bytes()),
+                    cb: on_load_callback,
+                    cb_data: &r.cb as *const OnLoadCallback as *mut c_void,
+                }
+            })
+            .collect();
+
+        PluginRegistration {
+            name: plugin.name().to_string(),
+            _on_resolve: build.on_resolve,
+            _on_load: build.on_load,
+            ffiapi_on_resolve,
+            ffiapi_on_load,
+        }
+    }
+
+    // WARNING: The PluginRegistration must live for the lifetime of FfiapiPlugin.
+    pub(crate) unsafe fn to_ffiapi_unmanaged(&self) -> FfiapiPlugin {
+        FfiapiPlugin {
+            name: GoString::from_bytes_unmanaged(self.name.as_bytes()),
+            on_resolve: get_allocation_pointer(&self.ffiapi_on_resolve),
+            on_resolve_len: self.ffiapi_on_resolve.len(),
+            on_load: get_allocation_pointer(&self.ffiapi_on_load),
+            on_load_len: self.ffiapi_on_load.len(),
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::sync::{Condvar, Mutex, MutexGuard, OnceLock, PoisonError};
+use std::thread;
+use std::time::Instant;
+
+use crate::panic::catch_panic;
+
+// A single background thread runs all scheduled timeouts, so that timeouts don't require an async
+// runtime or a thread per operation. Since every later timeout depends on it, the thread must
+// survive panics in the functions it runs.
+
+struct Entry {
+    deadline: Instant,
+    f: Box<dyn FnOnce() + Send>,
+}
+
+// BinaryHeap is a max-heap, so entries are ordered by reversed deadline to pop the earliest first.
+impl Ord for Entry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.deadline.cmp(&self.deadline)
+    }
+}
+
+impl PartialOrd for Entry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Entry {
+    fn eq(&self, other: &Self) -> bool {
+        self.deadline == other.deadline
+    }
+}
+
+impl Eq for Entry {}
+
+struct Timer {
+    entries: Mutex<BinaryHeap<Entry>>,
+    changed: Condvar,
+}
+
+static TIMER: OnceLock<&'static Timer> = OnceLock::new();
+
+impl Timer {
+    // The heap is never left inconsistent, so a poisoned lock is safe to keep using.
+    fn lock(&self) -> MutexGuard<'_, BinaryHeap<Entry>> {
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+fn run(timer: &Timer) {
+    let mut entries = timer.lock();
+    loop {
+        let now = Instant::now();
+        match entries.peek().map(|e| e.deadline) {
+            Some(deadline) if deadline <= now => {
+                let entry = entries.pop().unwrap();
+                // Don't hold the lock while running, as `f` may schedule more timeouts.
+                drop(entries);
+                // The panic hook has already reported any panic, so there's nothing more to do.
+                let _ = catch_panic(entry.f);
+                entries = timer.lock();
+            }
+            Some(deadline) => {
+                entries = timer
+                    .changed
+                    .wait_timeout(entries, deadline - now)
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .0;
+            }
+            None => {
+                entries = timer
+                    .changed
+                    .wait(entries)
+                    .unwrap_or_else(PoisonError::into_inner);
+            }
+        };
+    }
+}
+
+// Calls `f` from the timer thread once `deadline` has passed.
+pub(crate) fn schedule<F>(deadline: Instant, f: F)
+where
+    F: FnOnce(),
+    F: Send + 'static,
+{
+    let timer = *TIMER.get_or_init(|| {
+        let timer: &'static Timer = Box::leak(Box::new(Timer {
+            entries: Mutex::new(BinaryHeap::new()),
+            changed: Condvar::new(),
+        }));
+        thread::Builder::new()
+            .name("esbuild-rs-timer".to_string())
+            .spawn(move || run(timer))
+            .expect("spawn timer thread");
+        timer
+    });
+    timer.lock().push(Entry {
+        deadline,
+        f: Box::new(f),
+    });
+    timer.changed.notify_one();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    #[test]
+    fn survives_panicking_entry() {
+        schedule(Instant::now(), || panic!("timer entry panicked"));
+        let (sender, receiver) = mpsc::channel();
+        schedule(Instant::now() + Duration::from_millis(10), move || {
+            sender.send(()).unwrap();
+        });
+        receiver
+            .recv_timeout(Duration::from_secs(5))
+            .expect("later entry should still run");
+    }
+}
@@ -0,0 +1,250 @@
+//! Futures for running builds and transforms from a [Tokio](https://tokio.rs) runtime, enabled by
+//! the `tokio` feature. Results are delivered using Tokio's oneshot channels, so awaiting them
+//! takes part in Tokio's cooperative scheduling like any other Tokio resource.
+
+use std::future::Future;
+use std::panic;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::thread;
+
+use ::tokio::sync::oneshot;
+
+use crate::api::build::start_build;
+use crate::api::cancel::CancelHandle;
+use crate::api::transform::start_transform;
+use crate::panic::OnPanic;
+use crate::wrapper::{BuildOptions, BuildResult, TransformOptions, TransformResult};
+
+/// A handle to a build or transform started by `spawn_build` or `spawn_transform`, which can be
+/// awaited for its result. Like Tokio's JoinHandle, dropping it detaches the operation rather than
+/// cancelling it; use `abort` to cancel it. If a panic occurs while completing the operation, such
+/// as in the options' executor, it's resumed when the handle is awaited.
+pub struct JoinHandle<T> {
+    receiver: oneshot::Receiver<thread::Result<T>>,
+    cancel: CancelHandle,
+}
+
+// Returns the callbacks that complete a JoinHandle with a result or a panic, whichever comes first,
+// and the receiving end for the JoinHandle.
+fn completing<T>() -> (
+    impl FnOnce(T) + Send + 'static,
+    OnPanic,
+    oneshot::Receiver<thread::Result<T>>,
+)
+where
+    T: Send + 'static,
+{
+    let (sender, receiver) = oneshot::channel();
+    let sender = Arc::new(Mutex::new(Some(sender)));
+    let panic_sender = sender.clone();
+    let cb = move |result| {
+        if let Some(sender) = sender.lock().unwrap().take() {
+            // The handle may have been dropped.
+            let _ = sender.send(Ok(result));
+        };
+    };
+    let on_panic: OnPanic = Box::new(move |payload| {
+        // The panic may have happened while the lock was held.
+        let sender = panic_sender
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .take();
+        if let Some(sender) = sender {
+            let _ = sender.send(Err(payload));
+        };
+    });
+    (cb, on_panic, receiver)
+}
+
+impl<T> JoinHandle<T> {
+    /// Cancels the operation. Awaiting the handle will still return a result, with an error.
+    pub fn abort(&self) {
+        self.cancel.cancel();
+    }
+}
+
+impl<T> Future for JoinHandle<T> {
+    type Output = T;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.receiver).poll(cx).map(|result| {
+            // Go always calls back, even when cancelled, so the sender is never dropped unused.
+            match result.expect("esbuild callback dropped") {
+                Ok(result) => result,
+                Err(payload) => panic::resume_unwind(payload),
+            }
+        })
+    }
+}
+
+/// A Future for a build or transform started by `build` or `transform`. Dropping it before it
+/// completes cancels the operation.
+pub struct EsbuildFuture<T> {
+    handle: JoinHandle<T>,
+    completed: bool,
+}
+
+impl<T> EsbuildFuture<T> {
+    /// Cancels the operation. The Future will still complete, with an error.
+    pub fn cancel(&self) {
+        self.handle.abort();
+    }
+}
+
+impl<T> Drop for EsbuildFuture<T> {
+    fn drop(&mut self) {
+        if !self.completed {
+            self.handle.abort();
+        };
+    }
+}
+
+impl<T> Future for EsbuildFuture<T> {
+    type Output = T;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let result = Pin::new(&mut self.handle).poll(cx);
+        if result.is_ready() {
+            self.completed = true;
+        };
+        result
+    }
+}
+
+/// This function starts a build like `build_direct`, returning a JoinHandle for its result.
+///
+/// # Arguments
+///
+/// * `options` - Built BuildOptions created from a BuildOptionsBuilder. A reference will be held on
+///   the Arc until the build completes.
+///
+/// # Examples
+///
+/// ```
+/// use esbuild_rs::BuildOptionsBuilder;
+/// use esbuild_rs::tokio::spawn_build;
+///
+/// #[tokio::main(flavor = "multi_thread")]
+/// async fn main() {
+///   let mut options_builder = BuildOptionsBuilder::new();
+///   options_builder.entry_points.push("index.js".to_string());
+///   let options = options_builder.build();
+///
+///   let handle = spawn_build(options);
+///   let res = handle.await;
+/// }
+/// ```
+pub fn spawn_build(options: Arc<BuildOptions>) -> JoinHandle<BuildResult> {
+    let (cb, on_panic, receiver) = completing();
+    let cancel = start_build(options, cb, Some(on_panic));
+    JoinHandle { receiver, cancel }
+}
+
+/// This function starts a transform like `transform_direct`, returning a JoinHandle for its result.
+///
+/// # Arguments
+///
+/// * `code` - Source code to transform. Must be UTF-8. A reference will be held on the Arc until
+///   the transform completes.
+/// * `options` - Built TransformOptions created from a TransformOptionsBuilder. A reference will be
+///   held on the Arc until the transform completes.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+/// use esbuild_rs::TransformOptionsBuilder;
+/// use esbuild_rs::tokio::spawn_transform;
+///
+/// #[tokio::main(flavor = "multi_thread")]
+/// async fn main() {
+///   let options = TransformOptionsBuilder::new().build();
+///   let handles = (0..8)
+///     .map(|i| spawn_transform(Arc::new(format!("let x = {};", i).into_bytes()), options.clone()))
+///     .collect::<Vec<_>>();
+///   for handle in handles {
+///     println!("{}", handle.await.code);
+///   }
+/// }
+/// ```
+pub fn spawn_transform(
+    code: Arc<Vec<u8>>,
+    options: Arc<TransformOptions>,
+) -> JoinHandle<TransformResult> {
+    let (cb, on_panic, receiver) = completing();
+    let cancel = start_transform(code, options, cb, Some(on_panic));
+    JoinHandle { receiver, cancel }
+}
+
+/// Tokio-native equivalent of `esbuild_rs::build`. Dropping the Future before it completes cancels
+/// the build.
+///
+/// # Arguments
+///
+/// * `options` - Built BuildOptions created from a BuildOptionsBuilder. A reference will be held on
+///   the Arc until the build completes.
+///
+/// # Examples
+///
+/// ```
+/// use esbuild_rs::BuildOptionsBuilder;
+/// use esbuild_rs::tokio::build;
+///
+/// #[tokio::main(flavor = "multi_thread")]
+/// async fn main() {
+///   let mut options_builder = BuildOptionsBuilder::new();
+///   options_builder.entry_points.push("index.js".to_string());
+///   let options = options_builder.build();
+///
+///   let res = build(options).await;
+/// }
+/// ```
+pub fn build(options: Arc<BuildOptions>) -> EsbuildFuture<BuildResult> {
+    EsbuildFuture {
+        handle: spawn_build(options),
+        completed: false,
+    }
+}
+
+/// Tokio-native equivalent of `esbuild_rs::transform`. Dropping the Future before it completes
+/// cancels the transform.
+///
+/// # Arguments
+///
+/// * `code` - Source code to transform. Must be UTF-8. A reference will be held on the Arc until
+///   the transform completes.
+/// * `options` - Built TransformOptions created from a TransformOptionsBuilder. A reference will be
+///   held on the Arc until the transform completes.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+/// use std::time::Duration;
+/// use esbuild_rs::TransformOptionsBuilder;
+/// use esbuild_rs::tokio::transform;
+///
+/// #[tokio::main(flavor = "multi_thread")]
+/// async fn main() {
+///   let src = Arc::new(b"let x = NAME;".to_vec());
+///
+///   let mut options_builder = TransformOptionsBuilder::new();
+///   options_builder.define.insert("NAME".to_string(), "world".to_string());
+///   let options = options_builder.build();
+///
+///   // Timing out drops the Future, which cancels the transform.
+///   let res = tokio::time::timeout(Duration::from_secs(1), transform(src, options)).await.unwrap();
+///   assert_eq!(res.code.as_str().unwrap(), "let x = world;\n");
+/// }
+/// ```
+pub fn transform(
+    code: Arc<Vec<u8>>,
+    options: Arc<TransformOptions>,
+) -> EsbuildFuture<TransformResult> {
+    EsbuildFuture {
+        handle: spawn_transform(code, options),
+        completed: false,
+    }
+}
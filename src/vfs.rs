@@ -0,0 +1,182 @@
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::mem;
+use std::os::raw::c_void;
+use std::ptr;
+
+use libc::size_t;
+
+use crate::bridge::{FfiapiVirtualFs, FfiapiVirtualFsDirEntry};
+use crate::panic::catch_panic;
+use crate::wrapper::StrContainer;
+
+/// An in-memory file system for builds to read input files from, instead of or on top of the real
+/// file system. Attach it using `BuildOptionsBuilder::fs`.
+///
+/// Paths should be absolute, and are usually under `abs_working_dir`. Relative paths are resolved
+/// against `abs_working_dir`, or the current directory if it's empty. Backslashes are treated as
+/// forward slashes, so Windows paths can be used too. Directories exist implicitly for every file.
+///
+/// # Examples
+///
+/// ```
+/// use esbuild_rs::{BuildOptionsBuilder, build_sync, VirtualFs};
+///
+/// fn main() {
+///   let mut fs = VirtualFs::new();
+///   fs.files.insert("/app/index.js".to_string(), b"import {x} from './x.js'; console.log(x);".to_vec());
+///   fs.files.insert("/app/x.js".to_string(), b"export const x = 1;".to_vec());
+///
+///   let mut options_builder = BuildOptionsBuilder::new();
+///   options_builder.abs_working_dir = "/app".to_string();
+///   options_builder.entry_points.push("index.js".to_string());
+///   options_builder.bundle = true;
+///   options_builder.fs = Some(fs);
+///   let options = options_builder.build();
+///
+///   let res = build_sync(options);
+///   assert_eq!(res.errors.as_slice().len(), 0);
+/// }
+/// ```
+#[derive(Clone, Default)]
+pub struct VirtualFs {
+    /// Contents of each file, keyed by path.
+    pub files: HashMap<String, Vec<u8>>,
+    /// Whether to read files and directories from the real file system when they're not found
+    /// here. Directory listings merge both.
+    pub layered: bool,
+}
+
+impl VirtualFs {
+    pub fn new() -> VirtualFs {
+        VirtualFs::default()
+    }
+}
+
+fn normalize_path(path: &str) -> String {
+    path.replace('\\', "/")
+}
+
+// Whether a normalized path is absolute on either Unix or Windows, as Go may use either.
+fn is_absolute(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    path.starts_with('/')
+        || (bytes.len() >= 3 && bytes[0].is_ascii_alphabetic() && &bytes[1..3] == b":/")
+}
+
+// The VirtualFs held by BuildOptions, with normalized paths, for as long as Go may read from it.
+pub(crate) struct VirtualFsRegistration {
+    files: HashMap<String, Vec<u8>>,
+    layered: bool,
+}
+
+impl VirtualFsRegistration {
+    pub(crate) fn from_fs(fs: VirtualFs, abs_working_dir: &str) -> VirtualFsRegistration {
+        // Go only ever asks for absolute paths, so relative keys would otherwise never match.
+        let working_dir = if abs_working_dir.is_empty() {
+            env::current_dir()
+                .map(|dir| dir.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        } else {
+            abs_working_dir.to_string()
+        };
+        let working_dir = normalize_path(&working_dir);
+        VirtualFsRegistration {
+            files: fs
+                .files
+                .into_iter()
+                .map(|(path, contents)| {
+                    let path = normalize_path(&path);
+                    if is_absolute(&path) {
+                        (path, contents)
+                    } else {
+                        let path = path.trim_start_matches("./");
+                        (
+                            format!("{}/{}", working_dir.trim_end_matches('/'), path),
+                            contents,
+                        )
+                    }
+                })
+                .collect(),
+            layered: fs.layered,
+        }
+    }
+
+    // Returns the names of the entries in `dir`, and whether each is a directory.
+    fn read_dir(&self, dir: &str) -> BTreeMap<&str, bool> {
+        let prefix = format!("{}/", dir.trim_end_matches('/'));
+        let mut entries = BTreeMap::new();
+        for path in self.files.keys() {
+            if let Some(rest) = path.strip_prefix(&prefix) {
+                match rest.find('/') {
+                    Some(i) => entries.insert(&rest[..i], true),
+                    None => entries.insert(rest, false),
+                };
+            };
+        }
+        entries
+    }
+
+    // WARNING: The VirtualFsRegistration must live for the lifetime of FfiapiVirtualFs.
+    pub(crate) unsafe fn to_ffiapi_unmanaged(&self) -> FfiapiVirtualFs {
+        FfiapiVirtualFs {
+            read_file: vfs_read_file_callback,
+            read_dir: vfs_read_dir_callback,
+            cb_data: self as *const VirtualFsRegistration as *mut c_void,
+            layered: self.layered,
+        }
+    }
+}
+
+extern "C" fn vfs_read_file_callback(
+    cb_data: *mut c_void,
+    path: StrContainer,
+    contents: *mut StrContainer,
+) -> bool {
+    // A panic can't unwind into Go, so the file is treated as missing instead.
+    catch_panic(|| unsafe {
+        let fs = &*(cb_data as *const VirtualFsRegistration);
+        let path = normalize_path(&path.to_string_lossy());
+        match fs.files.get(&path) {
+            Some(data) => {
+                ptr::write(contents, StrContainer::from_bytes(data));
+                true
+            }
+            None => false,
+        }
+    })
+    .unwrap_or(false)
+}
+
+extern "C" fn vfs_read_dir_callback(
+    cb_data: *mut c_void,
+    path: StrContainer,
+    entries: *mut *mut FfiapiVirtualFsDirEntry,
+    entries_len: *mut size_t,
+) -> bool {
+    // A panic can't unwind into Go, so the directory is treated as missing instead.
+    catch_panic(|| unsafe {
+        let fs = &*(cb_data as *const VirtualFsRegistration);
+        let dir = fs.read_dir(&normalize_path(&path.to_string_lossy()));
+        if dir.is_empty() {
+            return false;
+        };
+        // Go frees the array and names once copied.
+        let len = dir.len();
+        let array = libc::malloc(len * mem::size_of::<FfiapiVirtualFsDirEntry>())
+            as *mut FfiapiVirtualFsDirEntry;
+        for (i, (name, is_dir)) in dir.into_iter().enumerate() {
+            ptr::write(
+                array.add(i),
+                FfiapiVirtualFsDirEntry {
+                    name: StrContainer::from_bytes(name.as_bytes()),
+                    is_dir,
+                },
+            );
+        }
+        ptr::write(entries, array);
+        ptr::write(entries_len, len);
+        true
+    })
+    .unwrap_or(false)
+}
@@ -1,16 +1,27 @@
+use std::borrow::Cow;
 use std::collections::HashMap;
-use std::fmt::{Display, Formatter};
+use std::fmt::{Debug, Display, Formatter};
+use std::ops::{BitOr, BitOrAssign, Deref};
 use std::os::raw::{c_char, c_void};
+use std::ptr::NonNull;
+use std::str::Utf8Error;
 use std::sync::Arc;
-use std::{convert, fmt, slice, str};
+use std::time::Duration;
+use std::{cmp, convert, fmt, mem, ptr, slice, str, vec};
 
 use libc::{ptrdiff_t, size_t};
 
 use crate::bridge::{
     get_allocation_pointer, FfiapiBuildOptions, FfiapiEngine, FfiapiEntryPoint,
-    FfiapiGoStringGoSlice, FfiapiLoader, FfiapiMapStringStringEntry, FfiapiTransformOptions,
-    GoString,
+    FfiapiGoStringGoSlice, FfiapiLoader, FfiapiMangleCacheEntry, FfiapiMangleCacheOption,
+    FfiapiMapStringStringEntry, FfiapiPlugin, FfiapiStdinOptions, FfiapiSupportedEntry,
+    FfiapiTransformOptions, FfiapiTransformResult, FfiapiVirtualFs, GoString,
 };
+use crate::error::{EsbuildError, TimeoutError};
+use crate::executor::Executor;
+use crate::feature::{CssFeature, JsFeature};
+use crate::plugin::{Plugin, PluginRegistration};
+use crate::vfs::{VirtualFs, VirtualFsRegistration};
 
 #[inline(always)]
 fn transform<I, S: IntoIterator<Item = I>, O, T: Fn(I) -> O>(src: S, mapper: T) -> Vec<O> {
@@ -19,39 +30,147 @@ fn transform<I, S: IntoIterator<Item = I>, O, T: Fn(I) -> O>(src: S, mapper: T)
 
 // We wrap C arrays allocated from Go and sent to us in SliceContainer, such as `*ffiapi_message`.
 // This will own the memory, make it usable as a slice, and drop using the matching deallocator.
+#[repr(C)]
 pub struct SliceContainer<T> {
     pub(crate) ptr: *mut T,
     pub(crate) len: usize,
 }
 
 impl<T> SliceContainer<T> {
+    // Moves `vec` into memory allocated using `malloc`, like the arrays Go sends us.
+    #[cfg(test)]
+    pub(crate) fn from_vec(vec: Vec<T>) -> SliceContainer<T> {
+        unsafe {
+            let ptr = libc::malloc(cmp::max(vec.len(), 1) * mem::size_of::<T>()) as *mut T;
+            let len = vec.len();
+            for (i, v) in vec.into_iter().enumerate() {
+                ptr::write(ptr.add(i), v);
+            }
+            SliceContainer { ptr, len }
+        }
+    }
+
     pub fn as_slice(&self) -> &[T] {
+        if self.len == 0 {
+            // Go may send NULL for empty arrays, which isn't a valid slice pointer.
+            return &[];
+        };
         unsafe { slice::from_raw_parts(self.ptr, self.len) }
     }
+
+    /// Moves the elements out into a Vec, freeing the array allocated by Go.
+    pub fn into_vec(self) -> Vec<T> {
+        let mut vec = Vec::with_capacity(self.len);
+        unsafe {
+            for i in 0..self.len {
+                vec.push(ptr::read(self.ptr.add(i)));
+            }
+            // We pass `malloc` to Go as the allocator.
+            libc::free(self.ptr as *mut c_void);
+        };
+        // The elements have been moved out, so we must not drop them again.
+        mem::forget(self);
+        vec
+    }
 }
 
 unsafe impl<T> Send for SliceContainer<T> {}
 
 unsafe impl<T> Sync for SliceContainer<T> {}
 
+impl<T: Debug> Debug for SliceContainer<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.as_slice().fmt(f)
+    }
+}
+
 impl<T> convert::AsRef<[T]> for SliceContainer<T> {
     fn as_ref(&self) -> &[T] {
         self.as_slice()
     }
 }
 
+impl<T> IntoIterator for SliceContainer<T> {
+    type Item = T;
+    type IntoIter = vec::IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.into_vec().into_iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a SliceContainer<T> {
+    type Item = &'a T;
+    type IntoIter = slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.as_slice().iter()
+    }
+}
+
 impl<T> Drop for SliceContainer<T> {
     fn drop(&mut self) {
         unsafe {
-            for i in 0..self.len {
-                drop(self.ptr.offset(i as isize));
-            }
+            if self.len > 0 {
+                // Elements may own memory allocated by Go too, such as the strings in Message.
+                ptr::drop_in_place(ptr::slice_from_raw_parts_mut(self.ptr, self.len));
+            };
             // We pass `malloc` to Go as the allocator.
             libc::free(self.ptr as *mut c_void);
         };
     }
 }
 
+// We wrap single C structs allocated from Go and sent to us by pointer in BoxContainer, such as
+// `*ffiapi_location`. This will own the memory, make it usable as a reference, and drop using the
+// matching deallocator. Wrap in Option where Go may send NULL.
+#[repr(transparent)]
+pub struct BoxContainer<T> {
+    ptr: NonNull<T>,
+}
+
+impl<T> BoxContainer<T> {
+    // Moves `value` into memory allocated using `malloc`, like the structs Go sends us.
+    #[cfg(test)]
+    pub(crate) fn new(value: T) -> BoxContainer<T> {
+        unsafe {
+            let ptr = libc::malloc(cmp::max(mem::size_of::<T>(), 1)) as *mut T;
+            ptr::write(ptr, value);
+            BoxContainer {
+                ptr: NonNull::new(ptr).unwrap(),
+            }
+        }
+    }
+}
+
+impl<T> Deref for BoxContainer<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { self.ptr.as_ref() }
+    }
+}
+
+impl<T: Debug> Debug for BoxContainer<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.deref().fmt(f)
+    }
+}
+
+unsafe impl<T> Send for BoxContainer<T> {}
+
+unsafe impl<T> Sync for BoxContainer<T> {}
+
+impl<T> Drop for BoxContainer<T> {
+    fn drop(&mut self) {
+        unsafe {
+            ptr::drop_in_place(self.ptr.as_ptr());
+            // We pass `malloc` to Go as the allocator.
+            libc::free(self.ptr.as_ptr() as *mut c_void);
+        };
+    }
+}
+
 // This is the ffiapi_string struct in C; we declare it here to avoid having to needlessly rewrap in StrContainer.
 // This will own the memory, make it usable as a str, and drop using the matching deallocator.
 #[repr(C)]
@@ -61,8 +180,35 @@ pub struct StrContainer {
 }
 
 impl StrContainer {
-    pub fn as_str(&self) -> &str {
-        unsafe { str::from_utf8_unchecked(slice::from_raw_parts(self.data as *mut u8, self.len)) }
+    // Copies `data` into memory allocated using `malloc`, for sending to Go, which will free it.
+    pub(crate) fn from_bytes(data: &[u8]) -> StrContainer {
+        unsafe {
+            // Always allocate at least one byte, as `malloc(0)` may return NULL.
+            let ptr = libc::malloc(cmp::max(data.len(), 1)) as *mut c_char;
+            ptr::copy_nonoverlapping(data.as_ptr(), ptr as *mut u8, data.len());
+            StrContainer {
+                len: data.len(),
+                data: ptr,
+            }
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        if self.len == 0 {
+            // Go may send NULL for empty strings, which isn't a valid slice pointer.
+            return &[];
+        };
+        unsafe { slice::from_raw_parts(self.data as *mut u8, self.len) }
+    }
+
+    /// Strings from Go aren't guaranteed to be UTF-8, such as the data of output files produced by
+    /// the binary loader, so this is checked.
+    pub fn as_str(&self) -> Result<&str, Utf8Error> {
+        str::from_utf8(self.as_bytes())
+    }
+
+    pub fn to_string_lossy(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(self.as_bytes())
     }
 }
 
@@ -70,15 +216,21 @@ unsafe impl Send for StrContainer {}
 
 unsafe impl Sync for StrContainer {}
 
-impl convert::AsRef<str> for StrContainer {
-    fn as_ref(&self) -> &str {
-        self.as_str()
+impl convert::AsRef<[u8]> for StrContainer {
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl Debug for StrContainer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&self.to_string_lossy(), f)
     }
 }
 
 impl Display for StrContainer {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        self.as_ref().fmt(f)
+        Display::fmt(&self.to_string_lossy(), f)
     }
 }
 
@@ -91,26 +243,53 @@ impl Drop for StrContainer {
     }
 }
 
-// This is the ffiapi_message struct in C; we declare it here to avoid having to needlessly rewrap in Message.
+// This is the ffiapi_location struct in C; we declare it here to avoid having to needlessly rewrap in Location.
 #[repr(C)]
-pub struct Message {
+#[derive(Debug)]
+pub struct Location {
     pub file: StrContainer,
+    pub namespace: StrContainer,
+    // 1-based.
     pub line: ptrdiff_t,
+    // 0-based, in bytes.
     pub column: ptrdiff_t,
+    // In bytes.
     pub length: ptrdiff_t,
+    pub line_text: StrContainer,
+    pub suggestion: StrContainer,
+}
+
+// This is the ffiapi_note struct in C; we declare it here to avoid having to needlessly rewrap in Note.
+#[repr(C)]
+#[derive(Debug)]
+pub struct Note {
     pub text: StrContainer,
+    pub location: Option<BoxContainer<Location>>,
+}
+
+// This is the ffiapi_message struct in C; we declare it here to avoid having to needlessly rewrap in Message.
+#[repr(C)]
+#[derive(Debug)]
+pub struct Message {
+    pub id: StrContainer,
+    pub plugin_name: StrContainer,
+    pub text: StrContainer,
+    pub location: Option<BoxContainer<Location>>,
+    pub notes: SliceContainer<Note>,
+    // esbuild allows any value here; it's converted to a string by Go.
+    pub detail: StrContainer,
 }
 
 impl Display for Message {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "{} [{}:{}:{}]",
-            self.text.as_str(),
-            self.file.as_str(),
-            self.line,
-            self.column
-        )
+        if !self.plugin_name.as_bytes().is_empty() {
+            write!(f, "[plugin {}] ", self.plugin_name)?;
+        };
+        write!(f, "{}", self.text)?;
+        if let Some(loc) = &self.location {
+            write!(f, " [{}:{}:{}]", loc.file, loc.line, loc.column)?;
+        };
+        Ok(())
     }
 }
 
@@ -121,6 +300,17 @@ pub struct OutputFile {
     pub data: StrContainer,
 }
 
+impl OutputFile {
+    pub fn as_bytes(&self) -> &[u8] {
+        self.data.as_bytes()
+    }
+
+    /// Only text outputs are guaranteed to be UTF-8; assets may contain arbitrary bytes.
+    pub fn as_str(&self) -> Result<&str, Utf8Error> {
+        self.data.as_str()
+    }
+}
+
 #[derive(Copy, Clone)]
 pub enum Charset {
     Default,
@@ -128,6 +318,45 @@ pub enum Charset {
     UTF8,
 }
 
+/// A set of constructs to remove from the output, which can be combined using `|`.
+///
+/// # Examples
+///
+/// ```
+/// use esbuild_rs::{DropSet, TransformOptionsBuilder};
+///
+/// let mut options_builder = TransformOptionsBuilder::new();
+/// options_builder.drop = DropSet::CONSOLE | DropSet::DEBUGGER;
+/// ```
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct DropSet(u8);
+
+impl DropSet {
+    pub const NONE: DropSet = DropSet(0);
+    /// Calls to `console` methods, including the evaluation of their arguments.
+    pub const CONSOLE: DropSet = DropSet(1 << 0);
+    /// `debugger` statements.
+    pub const DEBUGGER: DropSet = DropSet(1 << 1);
+
+    pub fn contains(self, other: DropSet) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for DropSet {
+    type Output = DropSet;
+
+    fn bitor(self, rhs: DropSet) -> DropSet {
+        DropSet(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for DropSet {
+    fn bitor_assign(&mut self, rhs: DropSet) {
+        self.0 |= rhs.0;
+    }
+}
+
 #[derive(Copy, Clone)]
 pub enum EngineName {
     Chrome,
@@ -138,11 +367,16 @@ pub enum EngineName {
     Safari,
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
 pub enum Format {
+    #[cfg_attr(feature = "serde", serde(skip))]
     Default,
+    #[cfg_attr(feature = "serde", serde(rename = "iife"))]
     IIFE,
+    #[cfg_attr(feature = "serde", serde(rename = "cjs"))]
     CommonJS,
+    #[cfg_attr(feature = "serde", serde(rename = "esm"))]
     ESModule,
 }
 
@@ -150,6 +384,9 @@ pub enum Format {
 pub enum JSXMode {
     Transform,
     Preserve,
+    // The React 17+ automatic runtime, which imports `jsx` from `jsx_import_source` instead of
+    // calling `jsx_factory`.
+    Automatic,
 }
 
 #[derive(Copy, Clone)]
@@ -234,6 +471,141 @@ pub struct EntryPoint {
     pub output_path: String,
 }
 
+/// An entry point provided in memory rather than read from the file system, equivalent to
+/// esbuild's `stdin` option.
+///
+/// # Examples
+///
+/// ```
+/// use esbuild_rs::{BuildOptionsBuilder, build_sync, Loader, StdinOptions};
+///
+/// fn main() {
+///   let mut options_builder = BuildOptionsBuilder::new();
+///   options_builder.stdin = Some(StdinOptions {
+///     contents: b"export * from './routes/home.js';".to_vec(),
+///     resolve_dir: "src".to_string(),
+///     sourcefile: "routes.js".to_string(),
+///     loader: Loader::JS,
+///   });
+///   options_builder.bundle = true;
+///   let options = options_builder.build();
+///
+///   let res = build_sync(options);
+/// }
+/// ```
+#[derive(Clone)]
+pub struct StdinOptions {
+    pub contents: Vec<u8>,
+    /// Directory to resolve imports in `contents` from.
+    pub resolve_dir: String,
+    /// Name of the file to use in error messages and source maps.
+    pub sourcefile: String,
+    pub loader: Loader,
+}
+
+/// The name chosen for a property by property mangling, or that the property must not be mangled.
+///
+/// With the `serde` feature, this is (de)serialized as either the string name or `false`, the same
+/// as esbuild's JSON mangle cache.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize, serde::Serialize),
+    serde(from = "RawMangleCacheEntry", into = "RawMangleCacheEntry")
+)]
+pub enum MangleCacheEntry {
+    Mangled(String),
+    Reserved,
+}
+
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(untagged)]
+enum RawMangleCacheEntry {
+    Mangled(String),
+    Reserved(bool),
+}
+
+#[cfg(feature = "serde")]
+impl From<RawMangleCacheEntry> for MangleCacheEntry {
+    fn from(raw: RawMangleCacheEntry) -> MangleCacheEntry {
+        match raw {
+            RawMangleCacheEntry::Mangled(name) => MangleCacheEntry::Mangled(name),
+            RawMangleCacheEntry::Reserved(_) => MangleCacheEntry::Reserved,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<MangleCacheEntry> for RawMangleCacheEntry {
+    fn from(entry: MangleCacheEntry) -> RawMangleCacheEntry {
+        match entry {
+            MangleCacheEntry::Mangled(name) => RawMangleCacheEntry::Mangled(name),
+            MangleCacheEntry::Reserved => RawMangleCacheEntry::Reserved(false),
+        }
+    }
+}
+
+/// Maps original property names to their MangleCacheEntry. Feeding the mangle cache from a result
+/// back into the options of later builds or transforms keeps mangled names stable between them.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+/// use esbuild_rs::{MangleCache, TransformOptionsBuilder, transform_sync};
+///
+/// fn main() {
+///   let mut cache = MangleCache::new();
+///   for src in &["x.foo_ = 1;", "y.foo_ = x.bar_;"] {
+///     let mut options_builder = TransformOptionsBuilder::new();
+///     options_builder.mangle_props = "_$".to_string();
+///     options_builder.mangle_cache = cache.clone();
+///     let options = options_builder.build();
+///
+///     let res = transform_sync(Arc::new(src.as_bytes().to_vec()), options);
+///     cache = res.mangle_cache;
+///   }
+///   assert!(cache.contains_key("foo_"));
+/// }
+/// ```
+pub type MangleCache = HashMap<String, MangleCacheEntry>;
+
+// WARNING: The MangleCache must live for the lifetime of the returned entries.
+unsafe fn mangle_cache_to_ffiapi_unmanaged(cache: &MangleCache) -> Vec<FfiapiMangleCacheOption> {
+    cache
+        .iter()
+        .map(|(key, entry)| {
+            let (value, reserved) = match entry {
+                MangleCacheEntry::Mangled(name) => (name.as_bytes(), false),
+                MangleCacheEntry::Reserved => (&b""[..], true),
+            };
+            FfiapiMangleCacheOption {
+                key: GoString::from_bytes_unmanaged(key.as_bytes()),
+                value: GoString::from_bytes_unmanaged(value),
+                reserved,
+            }
+        })
+        .collect()
+}
+
+pub(crate) fn mangle_cache_from_ffiapi(
+    entries: SliceContainer<FfiapiMangleCacheEntry>,
+) -> MangleCache {
+    entries
+        .as_slice()
+        .iter()
+        .map(|entry| {
+            let value = if entry.reserved {
+                MangleCacheEntry::Reserved
+            } else {
+                MangleCacheEntry::Mangled(entry.value.to_string_lossy().into_owned())
+            };
+            (entry.key.to_string_lossy().into_owned(), value)
+        })
+        .collect()
+}
+
 // BuildOptions and TransformOptions are nice APIs that mimics official Go API and use standard Rust
 // types. They're similar to Ffiapi*Options, but we create a separate struct for ease of use, as
 // Ffiapi*Options uses raw pointers which are difficult to mutate, either directly or in
@@ -247,6 +619,9 @@ pub struct BuildOptionsBuilder {
 
     pub target: Target,
     pub engines: Vec<Engine>,
+    // Overrides whether individual features are supported, on top of `target` and `engines`.
+    pub supported: HashMap<JsFeature, bool>,
+    pub supported_css: HashMap<CssFeature, bool>,
 
     pub minify_whitespace: bool,
     pub minify_identifiers: bool,
@@ -259,10 +634,23 @@ pub struct BuildOptionsBuilder {
     pub jsx_mode: JSXMode,
     pub jsx_factory: String,
     pub jsx_fragment: String,
+    // Defaults to "react". Only used with JSXMode::Automatic.
+    pub jsx_import_source: String,
+    // Only used with JSXMode::Automatic.
+    pub jsx_dev: bool,
 
     pub define: HashMap<String, String>,
     pub pure: Vec<String>,
     pub keep_names: bool,
+    // Go regular expression matching properties to mangle, or empty to disable property mangling.
+    pub mangle_props: String,
+    // Go regular expression matching properties to never mangle.
+    pub reserve_props: String,
+    pub mangle_quoted: bool,
+    pub mangle_cache: MangleCache,
+    pub drop: DropSet,
+    // Labeled statements with these labels are removed.
+    pub drop_labels: Vec<String>,
 
     pub global_name: String,
     pub bundle: bool,
@@ -294,20 +682,37 @@ pub struct BuildOptionsBuilder {
 
     pub entry_points: Vec<String>,
     pub entry_points_advanced: Vec<EntryPoint>,
+    pub stdin: Option<StdinOptions>,
 
     pub write: bool,
     pub allow_overwrite: bool,
     pub incremental: bool,
+
+    pub plugins: Vec<Arc<dyn Plugin>>,
+    pub fs: Option<VirtualFs>,
+
+    // Each build or rebuild still running this long after it's started is cancelled, and its result
+    // has `timed_out` set.
+    pub timeout: Option<Duration>,
+    // Runs completion callbacks instead of the Go thread that completed the build.
+    pub executor: Option<Arc<dyn Executor>>,
 }
 
 pub struct BuildOptions {
     // We keep data that fields of ffiapi_ptr point to.
     source_root: String,
     engines: Vec<FfiapiEngine>,
+    supported: Vec<FfiapiSupportedEntry>,
     jsx_factory: String,
     jsx_fragment: String,
+    jsx_import_source: String,
     define: Vec<FfiapiMapStringStringEntry>,
     pure: Vec<GoString>,
+    mangle_props: String,
+    reserve_props: String,
+    mangle_cache: MangleCache,
+    ffiapi_mangle_cache: Vec<FfiapiMangleCacheOption>,
+    drop_labels: Vec<GoString>,
     global_name: String,
     outfile: String,
     outdir: String,
@@ -330,6 +735,16 @@ pub struct BuildOptions {
     asset_names: String,
     entry_points: Vec<GoString>,
     entry_points_advanced: Vec<FfiapiEntryPoint>,
+    stdin: Option<StdinOptions>,
+    ffiapi_stdin: Option<FfiapiStdinOptions>,
+    // Plugin callbacks must outlive any build using these options.
+    _plugins: Vec<PluginRegistration>,
+    ffiapi_plugins: Vec<FfiapiPlugin>,
+    // The VirtualFs callbacks must also outlive any build using these options.
+    fs: Option<VirtualFsRegistration>,
+    ffiapi_fs: Option<FfiapiVirtualFs>,
+    pub(crate) timeout: Option<Duration>,
+    pub(crate) executor: Option<Arc<dyn Executor>>,
     pub(crate) ffiapi_ptr: *const FfiapiBuildOptions,
 }
 
@@ -353,6 +768,8 @@ impl BuildOptionsBuilder {
             sources_content: SourcesContent::Include,
             target: Target::Default,
             engines: vec![],
+            supported: HashMap::new(),
+            supported_css: HashMap::new(),
             minify_whitespace: false,
             minify_identifiers: false,
             minify_syntax: false,
@@ -363,9 +780,17 @@ impl BuildOptionsBuilder {
             jsx_mode: JSXMode::Transform,
             jsx_factory: "".to_string(),
             jsx_fragment: "".to_string(),
+            jsx_import_source: "".to_string(),
+            jsx_dev: false,
             define: Default::default(),
             pure: vec![],
             keep_names: false,
+            mangle_props: "".to_string(),
+            reserve_props: "".to_string(),
+            mangle_quoted: false,
+            mangle_cache: HashMap::new(),
+            drop: DropSet::NONE,
+            drop_labels: vec![],
             global_name: "".to_string(),
             bundle: false,
             preserve_symlinks: false,
@@ -394,22 +819,48 @@ impl BuildOptionsBuilder {
             asset_names: "".to_string(),
             entry_points: vec![],
             entry_points_advanced: vec![],
+            stdin: None,
             write: false,
             allow_overwrite: false,
             incremental: false,
+            plugins: vec![],
+            fs: None,
+            timeout: None,
+            executor: None,
         }
     }
 
     pub fn build(self) -> Arc<BuildOptions> {
+        let plugins = transform(self.plugins, |p| {
+            PluginRegistration::from_plugin(p.as_ref())
+        });
+        let ffiapi_plugins = transform(&plugins, |p| unsafe { p.to_ffiapi_unmanaged() });
+        let abs_working_dir = &self.abs_working_dir;
+        let fs = self
+            .fs
+            .map(|fs| VirtualFsRegistration::from_fs(fs, abs_working_dir));
         let mut res = Arc::new(BuildOptions {
             // We move into Arc first before creating pointers to data in it, as the move to the
             // heap by Arc should change the data's location.
             source_root: self.source_root,
             engines: transform(self.engines, FfiapiEngine::from_engine),
+            supported: self
+                .supported
+                .into_iter()
+                .map(|(f, s)| (f.as_str(), s))
+                .chain(self.supported_css.into_iter().map(|(f, s)| (f.as_str(), s)))
+                .map(|(f, s)| FfiapiSupportedEntry::from_supported_entry(f, s))
+                .collect(),
             jsx_factory: self.jsx_factory,
             jsx_fragment: self.jsx_fragment,
+            jsx_import_source: self.jsx_import_source,
             define: transform(self.define, FfiapiMapStringStringEntry::from_map_entry),
             pure: transform(self.pure, GoString::from_string),
+            mangle_props: self.mangle_props,
+            reserve_props: self.reserve_props,
+            mangle_cache: self.mangle_cache,
+            ffiapi_mangle_cache: vec![],
+            drop_labels: transform(self.drop_labels, GoString::from_string),
             global_name: self.global_name,
             outfile: self.outfile,
             outdir: self.outdir,
@@ -438,10 +889,30 @@ impl BuildOptionsBuilder {
                 self.entry_points_advanced,
                 FfiapiEntryPoint::from_entry_point,
             ),
+            stdin: self.stdin,
+            ffiapi_stdin: None,
+            _plugins: plugins,
+            ffiapi_plugins,
+            fs,
+            ffiapi_fs: None,
+            timeout: self.timeout,
+            executor: self.executor,
             ffiapi_ptr: std::ptr::null(),
         });
 
         unsafe {
+            let ffiapi_stdin = res.stdin.as_ref().map(|stdin| FfiapiStdinOptions {
+                contents: GoString::from_bytes_unmanaged(&stdin.contents),
+                resolve_dir: GoString::from_bytes_unmanaged(stdin.resolve_dir.as_bytes()),
+                sourcefile: GoString::from_bytes_unmanaged(stdin.sourcefile.as_bytes()),
+                loader: stdin.loader as u8,
+            });
+            Arc::get_mut(&mut res).unwrap().ffiapi_stdin = ffiapi_stdin;
+            let ffiapi_fs = res.fs.as_ref().map(|fs| fs.to_ffiapi_unmanaged());
+            Arc::get_mut(&mut res).unwrap().ffiapi_fs = ffiapi_fs;
+            let ffiapi_mangle_cache = mangle_cache_to_ffiapi_unmanaged(&res.mangle_cache);
+            Arc::get_mut(&mut res).unwrap().ffiapi_mangle_cache = ffiapi_mangle_cache;
+
             let ffiapi_ptr = Box::into_raw(Box::new(FfiapiBuildOptions {
                 source_map: self.source_map as u8,
                 source_root: GoString::from_bytes_unmanaged(res.source_root.as_bytes()),
@@ -450,6 +921,8 @@ impl BuildOptionsBuilder {
                 target: self.target as u8,
                 engines: get_allocation_pointer(&res.engines),
                 engines_len: res.engines.len(),
+                supported: get_allocation_pointer(&res.supported),
+                supported_len: res.supported.len(),
 
                 minify_whitespace: self.minify_whitespace,
                 minify_identifiers: self.minify_identifiers,
@@ -462,11 +935,20 @@ impl BuildOptionsBuilder {
                 jsx_mode: self.jsx_mode as u8,
                 jsx_factory: GoString::from_bytes_unmanaged(res.jsx_factory.as_bytes()),
                 jsx_fragment: GoString::from_bytes_unmanaged(res.jsx_fragment.as_bytes()),
+                jsx_import_source: GoString::from_bytes_unmanaged(res.jsx_import_source.as_bytes()),
+                jsx_dev: self.jsx_dev,
 
                 define: get_allocation_pointer(&res.define),
                 define_len: res.define.len(),
                 pure: FfiapiGoStringGoSlice::from_vec_unamanged(&res.pure),
                 keep_names: self.keep_names,
+                mangle_props: GoString::from_bytes_unmanaged(res.mangle_props.as_bytes()),
+                reserve_props: GoString::from_bytes_unmanaged(res.reserve_props.as_bytes()),
+                mangle_quoted: self.mangle_quoted,
+                mangle_cache: get_allocation_pointer(&res.ffiapi_mangle_cache),
+                mangle_cache_len: res.ffiapi_mangle_cache.len(),
+                drop: self.drop.0,
+                drop_labels: FfiapiGoStringGoSlice::from_vec_unamanged(&res.drop_labels),
 
                 global_name: GoString::from_bytes_unmanaged(res.global_name.as_bytes()),
                 bundle: self.bundle,
@@ -505,10 +987,22 @@ impl BuildOptionsBuilder {
                 entry_points: FfiapiGoStringGoSlice::from_vec_unamanged(&res.entry_points),
                 entry_points_advanced: get_allocation_pointer(&res.entry_points_advanced),
                 entry_points_advanced_len: res.entry_points_advanced.len(),
+                stdin: res
+                    .ffiapi_stdin
+                    .as_ref()
+                    .map_or(ptr::null(), |stdin| stdin as *const FfiapiStdinOptions),
 
                 write: self.write,
                 allow_overwrite: self.allow_overwrite,
                 incremental: self.incremental,
+
+                plugins: get_allocation_pointer(&res.ffiapi_plugins),
+                plugins_len: res.ffiapi_plugins.len(),
+
+                fs: res
+                    .ffiapi_fs
+                    .as_ref()
+                    .map_or(ptr::null(), |fs| fs as *const FfiapiVirtualFs),
             }));
             Arc::get_mut(&mut res).unwrap().ffiapi_ptr = ffiapi_ptr;
         };
@@ -522,6 +1016,30 @@ pub struct BuildResult {
     pub output_files: SliceContainer<OutputFile>,
     pub errors: SliceContainer<Message>,
     pub warnings: SliceContainer<Message>,
+    /// Only populated when property mangling is enabled using `mangle_props`.
+    pub mangle_cache: MangleCache,
+    /// Set if the operation was cancelled because it exceeded the `timeout` in its options.
+    pub timed_out: Option<TimeoutError>,
+}
+
+impl BuildResult {
+    /// Converts into an EsbuildError if the build had any errors.
+    pub fn into_result(self) -> Result<BuildResult, EsbuildError> {
+        if self.errors.as_slice().is_empty() {
+            return Ok(self);
+        };
+        let BuildResult {
+            errors,
+            warnings,
+            timed_out,
+            ..
+        } = self;
+        Err(EsbuildError {
+            errors,
+            warnings,
+            timed_out,
+        })
+    }
 }
 
 #[derive(Clone)]
@@ -534,6 +1052,9 @@ pub struct TransformOptionsBuilder {
     pub format: Format,
     pub global_name: String,
     pub engines: Vec<Engine>,
+    // Overrides whether individual features are supported, on top of `target` and `engines`.
+    pub supported: HashMap<JsFeature, bool>,
+    pub supported_css: HashMap<CssFeature, bool>,
 
     pub minify_whitespace: bool,
     pub minify_identifiers: bool,
@@ -546,6 +1067,10 @@ pub struct TransformOptionsBuilder {
     pub jsx_mode: JSXMode,
     pub jsx_factory: String,
     pub jsx_fragment: String,
+    // Defaults to "react". Only used with JSXMode::Automatic.
+    pub jsx_import_source: String,
+    // Only used with JSXMode::Automatic.
+    pub jsx_dev: bool,
 
     pub tsconfig_raw: String,
     pub footer: String,
@@ -554,9 +1079,24 @@ pub struct TransformOptionsBuilder {
     pub define: HashMap<String, String>,
     pub pure: Vec<String>,
     pub keep_names: bool,
+    // Go regular expression matching properties to mangle, or empty to disable property mangling.
+    pub mangle_props: String,
+    // Go regular expression matching properties to never mangle.
+    pub reserve_props: String,
+    pub mangle_quoted: bool,
+    pub mangle_cache: MangleCache,
+    pub drop: DropSet,
+    // Labeled statements with these labels are removed.
+    pub drop_labels: Vec<String>,
 
     pub source_file: String,
     pub loader: Loader,
+
+    // Each transform still running this long after it's started is cancelled, and its result has
+    // `timed_out` set.
+    pub timeout: Option<Duration>,
+    // Runs completion callbacks instead of the Go thread that completed the transform.
+    pub executor: Option<Arc<dyn Executor>>,
 }
 
 pub struct TransformOptions {
@@ -564,14 +1104,23 @@ pub struct TransformOptions {
     source_root: String,
     global_name: String,
     engines: Vec<FfiapiEngine>,
+    supported: Vec<FfiapiSupportedEntry>,
     jsx_factory: String,
     jsx_fragment: String,
+    jsx_import_source: String,
     tsconfig_raw: String,
     footer: String,
     banner: String,
     define: Vec<FfiapiMapStringStringEntry>,
     pure: Vec<GoString>,
+    mangle_props: String,
+    reserve_props: String,
+    mangle_cache: MangleCache,
+    ffiapi_mangle_cache: Vec<FfiapiMangleCacheOption>,
+    drop_labels: Vec<GoString>,
     source_file: String,
+    pub(crate) timeout: Option<Duration>,
+    pub(crate) executor: Option<Arc<dyn Executor>>,
     pub(crate) ffiapi_ptr: *const FfiapiTransformOptions,
 }
 
@@ -597,6 +1146,8 @@ impl TransformOptionsBuilder {
             format: Format::Default,
             global_name: "".to_string(),
             engines: vec![],
+            supported: HashMap::new(),
+            supported_css: HashMap::new(),
             minify_whitespace: false,
             minify_identifiers: false,
             minify_syntax: false,
@@ -607,14 +1158,24 @@ impl TransformOptionsBuilder {
             jsx_mode: JSXMode::Transform,
             jsx_factory: "".to_string(),
             jsx_fragment: "".to_string(),
+            jsx_import_source: "".to_string(),
+            jsx_dev: false,
             tsconfig_raw: "".to_string(),
             footer: "".to_string(),
             banner: "".to_string(),
             define: Default::default(),
             pure: vec![],
             keep_names: false,
+            mangle_props: "".to_string(),
+            reserve_props: "".to_string(),
+            mangle_quoted: false,
+            mangle_cache: HashMap::new(),
+            drop: DropSet::NONE,
+            drop_labels: vec![],
             source_file: "".to_string(),
             loader: Loader::None,
+            timeout: None,
+            executor: None,
         }
     }
 
@@ -625,18 +1186,36 @@ impl TransformOptionsBuilder {
             source_root: self.source_root,
             global_name: self.global_name,
             engines: transform(self.engines, FfiapiEngine::from_engine),
+            supported: self
+                .supported
+                .into_iter()
+                .map(|(f, s)| (f.as_str(), s))
+                .chain(self.supported_css.into_iter().map(|(f, s)| (f.as_str(), s)))
+                .map(|(f, s)| FfiapiSupportedEntry::from_supported_entry(f, s))
+                .collect(),
             jsx_factory: self.jsx_factory,
             jsx_fragment: self.jsx_fragment,
+            jsx_import_source: self.jsx_import_source,
             tsconfig_raw: self.tsconfig_raw,
             footer: self.footer,
             banner: self.banner,
             define: transform(self.define, FfiapiMapStringStringEntry::from_map_entry),
             pure: transform(self.pure, GoString::from_string),
+            mangle_props: self.mangle_props,
+            reserve_props: self.reserve_props,
+            mangle_cache: self.mangle_cache,
+            ffiapi_mangle_cache: vec![],
+            drop_labels: transform(self.drop_labels, GoString::from_string),
             source_file: self.source_file,
+            timeout: self.timeout,
+            executor: self.executor,
             ffiapi_ptr: std::ptr::null(),
         });
 
         unsafe {
+            let ffiapi_mangle_cache = mangle_cache_to_ffiapi_unmanaged(&res.mangle_cache);
+            Arc::get_mut(&mut res).unwrap().ffiapi_mangle_cache = ffiapi_mangle_cache;
+
             let ffiapi_ptr = Box::into_raw(Box::new(FfiapiTransformOptions {
                 source_map: self.source_map as u8,
                 source_root: GoString::from_bytes_unmanaged(res.source_root.as_bytes()),
@@ -647,6 +1226,8 @@ impl TransformOptionsBuilder {
                 global_name: GoString::from_bytes_unmanaged(res.global_name.as_bytes()),
                 engines: get_allocation_pointer(&res.engines),
                 engines_len: res.engines.len(),
+                supported: get_allocation_pointer(&res.supported),
+                supported_len: res.supported.len(),
 
                 minify_whitespace: self.minify_whitespace,
                 minify_identifiers: self.minify_identifiers,
@@ -659,6 +1240,8 @@ impl TransformOptionsBuilder {
                 jsx_mode: self.jsx_mode as u8,
                 jsx_factory: GoString::from_bytes_unmanaged(res.jsx_factory.as_bytes()),
                 jsx_fragment: GoString::from_bytes_unmanaged(res.jsx_fragment.as_bytes()),
+                jsx_import_source: GoString::from_bytes_unmanaged(res.jsx_import_source.as_bytes()),
+                jsx_dev: self.jsx_dev,
                 tsconfig_raw: GoString::from_bytes_unmanaged(res.tsconfig_raw.as_bytes()),
                 footer: GoString::from_bytes_unmanaged(res.footer.as_bytes()),
                 banner: GoString::from_bytes_unmanaged(res.banner.as_bytes()),
@@ -667,6 +1250,13 @@ impl TransformOptionsBuilder {
                 define_len: res.define.len(),
                 pure: FfiapiGoStringGoSlice::from_vec_unamanged(&res.pure),
                 keep_names: self.keep_names,
+                mangle_props: GoString::from_bytes_unmanaged(res.mangle_props.as_bytes()),
+                reserve_props: GoString::from_bytes_unmanaged(res.reserve_props.as_bytes()),
+                mangle_quoted: self.mangle_quoted,
+                mangle_cache: get_allocation_pointer(&res.ffiapi_mangle_cache),
+                mangle_cache_len: res.ffiapi_mangle_cache.len(),
+                drop: self.drop.0,
+                drop_labels: FfiapiGoStringGoSlice::from_vec_unamanged(&res.drop_labels),
 
                 source_file: GoString::from_bytes_unmanaged(res.source_file.as_bytes()),
                 loader: self.loader as u8,
@@ -683,4 +1273,39 @@ pub struct TransformResult {
     pub map: StrContainer,
     pub errors: SliceContainer<Message>,
     pub warnings: SliceContainer<Message>,
+    /// Only populated when property mangling is enabled using `mangle_props`.
+    pub mangle_cache: MangleCache,
+    /// Set if the operation was cancelled because it exceeded the `timeout` in its options.
+    pub timed_out: Option<TimeoutError>,
+}
+
+impl TransformResult {
+    pub(crate) fn from_ffiapi(res: FfiapiTransformResult) -> TransformResult {
+        TransformResult {
+            code: res.code,
+            map: res.map,
+            errors: res.errors,
+            warnings: res.warnings,
+            mangle_cache: mangle_cache_from_ffiapi(res.mangle_cache),
+            timed_out: None,
+        }
+    }
+
+    /// Converts into an EsbuildError if the transform had any errors.
+    pub fn into_result(self) -> Result<TransformResult, EsbuildError> {
+        if self.errors.as_slice().is_empty() {
+            return Ok(self);
+        };
+        let TransformResult {
+            errors,
+            warnings,
+            timed_out,
+            ..
+        } = self;
+        Err(EsbuildError {
+            errors,
+            warnings,
+            timed_out,
+        })
+    }
 }